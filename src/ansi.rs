@@ -6,17 +6,32 @@ use serde::Serialize;
 pub enum ANSISequence {
     Reset,
     Bold,
+    Dim,
     Italic,
     Underline,
     NotBold,
     NotItalic,
     NotUnderline,
+    Reverse,
+    NotReverse,
+    Conceal,
+    NotConceal,
+    Blink,
+    NotBlink,
+    // the 16 4-bit colors (0-7 standard, 8-15 high intensity), kept apart from the same indexes set
+    // through 38;5;n since renderers theme these
+    SetFG4(u8),
     SetFG8(u8),
     DefaultFG,
+    SetBG4(u8),
     SetBG8(u8),
     DefaultBG,
     SetFG24(u8, u8, u8),
     SetBG24(u8, u8, u8),
+    // underline color (58/59), e.g. for colored error squiggles
+    SetUnderlineColor8(u8),
+    SetUnderlineColor24(u8, u8, u8),
+    DefaultUnderlineColor,
 }
 
 impl ANSISequence {
@@ -28,13 +43,20 @@ impl ANSISequence {
         let matched = match seq[0] {
             0 => Some((ANSISequence::Reset, 1)),
             1 => Some((ANSISequence::Bold, 1)),
+            2 => Some((ANSISequence::Dim, 1)),
             3 => Some((ANSISequence::Italic, 1)),
             4 => Some((ANSISequence::Underline, 1)),
+            5 | 6 => Some((ANSISequence::Blink, 1)), // slow and rapid blink are treated the same
+            7 => Some((ANSISequence::Reverse, 1)),
+            8 => Some((ANSISequence::Conceal, 1)),
             22 => Some((ANSISequence::NotBold, 1)),
             23 => Some((ANSISequence::NotItalic, 1)),
             24 => Some((ANSISequence::NotUnderline, 1)),
+            25 => Some((ANSISequence::NotBlink, 1)),
+            27 => Some((ANSISequence::NotReverse, 1)),
+            28 => Some((ANSISequence::NotConceal, 1)),
             // https://en.wikipedia.org/wiki/ANSI_escape_code#3-bit_and_4-bit
-            30..=37 => Some((ANSISequence::SetFG8(seq[0] - 30), 1)), // 30-37 are the 4bit colors
+            30..=37 => Some((ANSISequence::SetFG4(seq[0] - 30), 1)), // 30-37 are the 4bit colors
             38 => match (seq.get(1), seq.get(2), seq.get(3), seq.get(4)) {
                 (Some(5), Some(0..=255), _, _) => Some((ANSISequence::SetFG8(seq[2]), 3)),
                 (Some(2), Some(0..=255), Some(0..=255), Some(0..=255)) => {
@@ -43,7 +65,7 @@ impl ANSISequence {
                 _ => None,
             },
             39 => Some((ANSISequence::DefaultFG, 1)),
-            40..=47 => Some((ANSISequence::SetBG8(seq[0] - 40), 1)), // 40-47 are the 4bit colors
+            40..=47 => Some((ANSISequence::SetBG4(seq[0] - 40), 1)), // 40-47 are the 4bit colors
             48 => match (seq.get(1), seq.get(2), seq.get(3), seq.get(4)) {
                 (Some(5), Some(0..=255), _, _) => Some((ANSISequence::SetBG8(seq[2]), 3)),
                 (Some(2), Some(0..=255), Some(0..=255), Some(0..=255)) => {
@@ -52,8 +74,19 @@ impl ANSISequence {
                 _ => None,
             },
             49 => Some((ANSISequence::DefaultBG, 1)),
-            90..=97 => Some((ANSISequence::SetFG8(seq[0] - 90 + 8), 1)), // 90-97 are the 4bit high intensity
-            100..=107 => Some((ANSISequence::SetBG8(seq[0] - 100 + 8), 1)), // 100-107 are the 4bit high intensity
+            // 58 takes the same 5;n and 2;r;g;b forms as 38 and 48
+            58 => match (seq.get(1), seq.get(2), seq.get(3), seq.get(4)) {
+                (Some(5), Some(0..=255), _, _) => {
+                    Some((ANSISequence::SetUnderlineColor8(seq[2]), 3))
+                }
+                (Some(2), Some(0..=255), Some(0..=255), Some(0..=255)) => {
+                    Some((ANSISequence::SetUnderlineColor24(seq[2], seq[3], seq[4]), 5))
+                }
+                _ => None,
+            },
+            59 => Some((ANSISequence::DefaultUnderlineColor, 1)),
+            90..=97 => Some((ANSISequence::SetFG4(seq[0] - 90 + 8), 1)), // 90-97 are the 4bit high intensity
+            100..=107 => Some((ANSISequence::SetBG4(seq[0] - 100 + 8), 1)), // 100-107 are the 4bit high intensity
             _ => None,
         };
 
@@ -63,87 +96,342 @@ impl ANSISequence {
         }
     }
 
+    // the SGR escape code for the sequence
+    pub fn to_escape(&self) -> String {
+        let params = match self {
+            ANSISequence::Reset => "0".to_string(),
+            ANSISequence::Bold => "1".to_string(),
+            ANSISequence::Dim => "2".to_string(),
+            ANSISequence::Italic => "3".to_string(),
+            ANSISequence::Underline => "4".to_string(),
+            ANSISequence::Blink => "5".to_string(),
+            ANSISequence::Reverse => "7".to_string(),
+            ANSISequence::Conceal => "8".to_string(),
+            ANSISequence::NotBold => "22".to_string(),
+            ANSISequence::NotItalic => "23".to_string(),
+            ANSISequence::NotUnderline => "24".to_string(),
+            ANSISequence::NotBlink => "25".to_string(),
+            ANSISequence::NotReverse => "27".to_string(),
+            ANSISequence::NotConceal => "28".to_string(),
+            ANSISequence::SetFG4(n @ 0..=7) => (30 + n).to_string(),
+            ANSISequence::SetFG4(n @ 8..=15) => (90 + n - 8).to_string(),
+            ANSISequence::SetFG4(n) | ANSISequence::SetFG8(n) => format!("38;5;{}", n),
+            ANSISequence::DefaultFG => "39".to_string(),
+            ANSISequence::SetBG4(n @ 0..=7) => (40 + n).to_string(),
+            ANSISequence::SetBG4(n @ 8..=15) => (100 + n - 8).to_string(),
+            ANSISequence::SetBG4(n) | ANSISequence::SetBG8(n) => format!("48;5;{}", n),
+            ANSISequence::DefaultBG => "49".to_string(),
+            ANSISequence::SetFG24(r, g, b) => format!("38;2;{};{};{}", r, g, b),
+            ANSISequence::SetBG24(r, g, b) => format!("48;2;{};{};{}", r, g, b),
+            ANSISequence::SetUnderlineColor8(n) => format!("58;5;{}", n),
+            ANSISequence::SetUnderlineColor24(r, g, b) => format!("58;2;{};{};{}", r, g, b),
+            ANSISequence::DefaultUnderlineColor => "59".to_string(),
+        };
+
+        format!("\x1b[{}m", params)
+    }
+
     pub fn from(seq: String) -> Option<Vec<Self>> {
-        let mut possible_seqs: Vec<u8> = seq
-            .split(';')
-            .map(|n| n.parse::<u8>())
-            .collect::<Result<Vec<u8>, _>>()
-            .ok()?;
+        // ESC[m without any parameters is the same as ESC[0m
+        if seq.is_empty() {
+            return Some(vec![ANSISequence::Reset]);
+        }
+
+        // only the valid prefix of the parameters is kept, e.g. 1;38;5;300 is still bold
+        let mut possible_seqs: Vec<u8> =
+            seq.split(';').map_while(|n| n.parse::<u8>().ok()).collect();
 
         let mut seqs = Vec::new();
         while !possible_seqs.is_empty() {
             let (matched, rest) = ANSISequence::match_seqs(possible_seqs);
             match matched {
                 Some(seq) => seqs.push(seq),
-                // if any part of the sequence fails to match treat the whole thing as invalid
-                None => return None,
+                // drop the rest of the sequence once any part of it fails to match
+                None => break,
             }
             possible_seqs = rest;
         }
 
+        // nothing matched at all, treat the whole thing as invalid
+        if seqs.is_empty() {
+            return None;
+        }
+
         Some(seqs)
     }
 }
 
-pub fn extract_ansi(raw: String) -> (String, HashMap<usize, Vec<ANSISequence>>) {
-    let mut scrubbed = String::new();
-    scrubbed.reserve(raw.len());
-    let mut ansi_map: HashMap<usize, Vec<ANSISequence>> = HashMap::new();
+// splits off an escape sequence left unterminated at the end of raw, e.g. "foo\x1b[3" -> ("foo", "\x1b[3")
+pub fn split_dangling_escape(raw: &str) -> (&str, &str) {
+    if let Some(idx) = raw.rfind('\x1b') {
+        let tail = &raw[idx + 1..];
+        let dangling = match tail.strip_prefix('[') {
+            Some(params) => params
+                .strip_prefix('?')
+                .unwrap_or(params)
+                .chars()
+                .all(|ch| ch.is_ascii_digit() || ch == ';'),
+            None => tail.is_empty(),
+        };
+
+        if dangling {
+            return raw.split_at(idx);
+        }
+    }
+
+    (raw, "")
+}
+
+// replays carriage returns and erase in line sequences (ESC[K, ESC[0K, ESC[1K, ESC[2K) like a
+// terminal would, so progress output and spinners that redraw a line collapse to their final frame.
+// Lines without an erase sequence or a carriage return followed by more output are returned as is
+pub fn apply_erase_in_line(raw: String) -> String {
+    let erases = ["\x1b[K", "\x1b[0K", "\x1b[1K", "\x1b[2K"]
+        .iter()
+        .any(|erase| raw.contains(erase));
+    if !erases && !raw.trim_end_matches('\r').contains('\r') {
+        return raw;
+    }
+
+    // each cell is a visible char, prefixed by any escape sequences written before it
+    let mut cells: Vec<String> = Vec::new();
+    let mut cursor = 0;
+    let mut pending = String::new();
 
     let mut chars = raw.chars().peekable();
     while let Some(ch) = chars.next() {
         match (ch, chars.peek()) {
-            // Matches start of ESC[<seq>m
             ('\x1b', Some('[')) => {
                 chars.next();
-                let mut acc = String::new();
-                let mut seqs: Option<Vec<ANSISequence>> = None;
-
-                // Read until we find 'm' or run out of chars
-                loop {
-                    match chars.next() {
-                        Some('m') => {
-                            seqs = ANSISequence::from(acc.clone());
-                            acc.push('m');
-                            break;
-                        }
-                        Some(ch) => {
-                            acc.push(ch);
-                        }
-                        None => {
-                            break;
-                        }
+                let mut params = String::new();
+                let mut last = None;
+                for ch in chars.by_ref() {
+                    // including the ? of private mode sequences, which extract_ansi drops
+                    if ch.is_ascii_digit() || ch == ';' || (ch == '?' && params.is_empty()) {
+                        params.push(ch);
+                    } else {
+                        last = Some(ch);
+                        break;
                     }
                 }
 
-                match seqs {
-                    // Found a valid sequence, push & mark the index
-                    Some(seqs) => match ansi_map.get_mut(&scrubbed.len()) {
-                        Some(existing) => existing.extend(seqs),
-                        None => {
-                            ansi_map.insert(scrubbed.len(), seqs);
-                        }
-                    },
-                    // Nothing found just push what we've seen
-                    None => {
-                        scrubbed.push_str("\x1b[");
-                        scrubbed.push_str(&acc);
+                // erased chars are dropped or blanked, but their escapes are kept since styles
+                // carry over to whatever is written after them
+                match (last, params.as_str()) {
+                    (Some('K'), "" | "0") => erase_to_end(&mut cells, cursor, &mut pending),
+                    (Some('K'), "1") => {
+                        let end = cursor.min(cells.len());
+                        cells[..end].iter_mut().for_each(blank);
+                    }
+                    (Some('K'), "2") => {
+                        erase_to_end(&mut cells, cursor, &mut pending);
+                        cells.iter_mut().for_each(blank);
+                    }
+                    // everything else (like SGR) is kept for extract_ansi
+                    _ => {
+                        pending.push_str("\x1b[");
+                        pending.push_str(&params);
+                        pending.extend(last);
                     }
                 }
             }
+            ('\r', _) => cursor = 0,
+            _ => {
+                // overwriting a cell keeps its escapes too
+                let cell = match cells.get(cursor) {
+                    Some(old) => format!("{}{}{}", escapes(old), pending, ch),
+                    None => format!("{}{}", pending, ch),
+                };
+                pending.clear();
+
+                // erasing to the start of the line can leave the cursor past the end
+                while cells.len() < cursor {
+                    cells.push(" ".to_string());
+                }
+
+                if cursor < cells.len() {
+                    cells[cursor] = cell;
+                } else {
+                    cells.push(cell);
+                }
+                cursor += 1;
+            }
+        }
+    }
+
+    let mut collapsed = cells.concat();
+    collapsed.push_str(&pending);
+    collapsed
+}
+
+// the escape sequences prefixing a cell's char
+fn escapes(cell: &str) -> &str {
+    cell.strip_suffix(|_| true).unwrap_or_default()
+}
+
+fn erase_to_end(cells: &mut Vec<String>, cursor: usize, pending: &mut String) {
+    if cursor < cells.len() {
+        let erased: String = cells
+            .drain(cursor..)
+            .map(|cell| escapes(&cell).to_string())
+            .collect();
+        pending.insert_str(0, &erased);
+    }
+}
+
+fn blank(cell: &mut String) {
+    *cell = format!("{} ", escapes(cell));
+}
+
+pub fn extract_ansi(raw: String) -> (String, HashMap<usize, Vec<ANSISequence>>) {
+    let (scrubbed, ansi_map, _) = extract_ansi_truncated(raw, usize::MAX);
+    (scrubbed, ansi_map)
+}
+
+// like extract_ansi, but only keeps the first max visible chars (and the escapes before them).
+// The rest is still scanned to count the dropped chars, which are returned too. Lines without
+// escapes are handed back whole, they're cheap to truncate by chars afterwards
+pub fn extract_ansi_truncated(
+    raw: String,
+    max: usize,
+) -> (String, HashMap<usize, Vec<ANSISequence>>, usize) {
+    // most lines have no escapes, hand those back as is instead of copying them
+    if !raw.contains(['\x1b', '\u{9b}']) {
+        return (raw, HashMap::new(), 0);
+    }
+
+    let mut scrubbed = String::new();
+    scrubbed.reserve(raw.len().min(max.saturating_mul(4)));
+    let mut ansi_map: HashMap<usize, Vec<ANSISequence>> = HashMap::new();
+    let mut room = max;
+    let mut dropped = 0;
+
+    let mut chars = raw.chars().peekable();
+    while let Some(ch) = chars.next() {
+        let introducer = match (ch, chars.peek()) {
+            // Matches start of ESC[<seq>m
+            ('\x1b', Some('[')) => {
+                chars.next();
+                "\x1b["
+            }
+            // Matches start of the 8-bit CSI form <CSI><seq>m
+            ('\u{9b}', _) => "\u{9b}",
             // No match, just push the char
             (_, _) => {
-                scrubbed.push(ch);
+                push_visible(&mut scrubbed, &mut ansi_map, ch, &mut room, &mut dropped);
+                continue;
+            }
+        };
+
+        // private mode sequences like ESC[?25l (hide the cursor) don't style anything, they're
+        // dropped once complete. Params and intermediates are 0x20-0x3f and the final byte 0x40-0x7e
+        if chars.peek() == Some(&'?') {
+            let mut acc = String::new();
+            let mut complete = false;
+            for ch in chars.by_ref() {
+                acc.push(ch);
+                match ch {
+                    ' '..='?' => {}
+                    '@'..='~' => {
+                        complete = true;
+                        break;
+                    }
+                    _ => break,
+                }
+            }
+
+            if !complete {
+                for ch in introducer.chars().chain(acc.chars()) {
+                    push_visible(&mut scrubbed, &mut ansi_map, ch, &mut room, &mut dropped);
+                }
+            }
+            continue;
+        }
+
+        let mut acc = String::new();
+        let mut seqs: Option<Vec<ANSISequence>> = None;
+
+        // Read until we find 'm' or run out of chars
+        loop {
+            match chars.next() {
+                Some('m') => {
+                    seqs = ANSISequence::from(acc.clone());
+                    acc.push('m');
+                    break;
+                }
+                Some(ch) => {
+                    acc.push(ch);
+                }
+                None => {
+                    break;
+                }
+            }
+        }
+
+        match seqs {
+            // Found a valid sequence, push & mark the index. Sequences at the same index are kept
+            // in source order, which is the order they are applied in. Past max they're dropped
+            Some(_) if dropped > 0 => {}
+            Some(seqs) => match ansi_map.get_mut(&scrubbed.len()) {
+                Some(existing) => existing.extend(seqs),
+                None => {
+                    ansi_map.insert(scrubbed.len(), seqs);
+                }
+            },
+            // Nothing found just push what we've seen
+            None => {
+                for ch in introducer.chars().chain(acc.chars()) {
+                    push_visible(&mut scrubbed, &mut ansi_map, ch, &mut room, &mut dropped);
+                }
             }
         }
     }
 
-    (scrubbed, ansi_map)
+    (scrubbed, ansi_map, dropped)
+}
+
+// pushes ch while there's room left, otherwise counts it as dropped. The escapes right before the
+// first dropped char are dropped with it, like Line::truncate does
+fn push_visible(
+    scrubbed: &mut String,
+    ansi_map: &mut HashMap<usize, Vec<ANSISequence>>,
+    ch: char,
+    room: &mut usize,
+    dropped: &mut usize,
+) {
+    if *room > 0 {
+        scrubbed.push(ch);
+        *room -= 1;
+    } else {
+        if *dropped == 0 {
+            ansi_map.remove(&scrubbed.len());
+        }
+        *dropped += 1;
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::style::{Color, Styles};
+
+    #[test]
+    fn dangling_escape() {
+        let cases = [
+            ("foo\u{1b}[3", ("foo", "\u{1b}[3")),
+            ("foo\u{1b}[", ("foo", "\u{1b}[")),
+            ("foo\u{1b}", ("foo", "\u{1b}")),
+            ("foo\u{1b}[38;5;", ("foo", "\u{1b}[38;5;")),
+            ("foo\u{1b}[?25", ("foo", "\u{1b}[?25")),
+            ("foo\u{1b}[31m", ("foo\u{1b}[31m", "")),
+            ("foo\u{1b}[31mbar", ("foo\u{1b}[31mbar", "")),
+            ("foo", ("foo", "")),
+        ];
+
+        for (raw, expected) in cases {
+            assert_eq!(split_dangling_escape(raw), expected, "{:?}", raw);
+        }
+    }
 
     #[test]
     fn reset() {
@@ -160,6 +448,40 @@ mod tests {
         assert_eq!(want.1, got.1);
     }
 
+    #[test]
+    fn truncated() {
+        let raw = "\u{1b}[1mab\u{1b}[0mcd\u{1b}[31mef\u{1b}[0m";
+        let (content, ansis, dropped) = extract_ansi_truncated(raw.to_string(), 4);
+        assert_eq!(content, "abcd");
+        assert_eq!(dropped, 2);
+        // the escape right before the first dropped char goes with it
+        assert_eq!(
+            ansis,
+            HashMap::from([
+                (0, vec![ANSISequence::Bold]),
+                (2, vec![ANSISequence::Reset])
+            ])
+        );
+
+        // escapes at the end are kept when nothing is dropped
+        let (content, ansis, dropped) = extract_ansi_truncated(raw.to_string(), 6);
+        assert_eq!(content, "abcdef");
+        assert_eq!(dropped, 0);
+        assert_eq!(ansis.len(), 4);
+    }
+
+    #[test]
+    fn empty_reset() {
+        let raw = "\u{1b}[1mfoo\u{1b}[mbar";
+        let got = extract_ansi(raw.to_string());
+        assert_eq!(got.0, "foobar");
+        assert_eq!(got.1.get(&3), Some(&vec![ANSISequence::Reset]));
+        assert_eq!(
+            extract_ansi("\u{1b}[mfoo".to_string()),
+            extract_ansi("\u{1b}[0mfoo".to_string())
+        );
+    }
+
     #[test]
     fn bold() {
         let raw = "\u{1b}[1mbold\u{1b}[22m";
@@ -205,6 +527,73 @@ mod tests {
         assert_eq!(want.1, got.1);
     }
 
+    #[test]
+    fn erase_in_line() {
+        let cases = [
+            // docker style progress frames
+            (
+                "Downloading  10%\r\u{1b}[KDownloading  55%\r\u{1b}[KDownloading 100%",
+                "Downloading 100%",
+            ),
+            // npm style, the final frame is shorter than the previous one
+            (
+                "[#####.....] fetching\r\u{1b}[0K[##########] done",
+                "[##########] done",
+            ),
+            // without erasing, a shorter frame leaves the tail of the previous one
+            ("abcdef\r\u{1b}[2KXY\u{1b}[K", "XY"),
+            ("abcdef\rXY\u{1b}[1K!", "  !def"),
+            // styles written before overwritten chars are kept
+            ("\u{1b}[31mabc\r\u{1b}[KX", "\u{1b}[31mX"),
+            ("\u{1b}[31mabc\u{1b}[0m\r\u{1b}[KX", "\u{1b}[31m\u{1b}[0mX"),
+            // a bare carriage return overwrites in place
+            ("foo\rbar", "bar"),
+            ("foobar\rXY", "XYobar"),
+            // braille spinner glyphs are multi-byte, frames overwrite whole chars
+            (
+                "⠋ building\r⠙ building\r\u{1b}[36m⠹\u{1b}[0m building",
+                "\u{1b}[36m⠹\u{1b}[0m building",
+            ),
+            // nothing is written after a trailing carriage return, so nothing is rewritten
+            ("foo\r", "foo\r"),
+            ("foo", "foo"),
+        ];
+
+        for (raw, expected) in cases {
+            assert_eq!(apply_erase_in_line(raw.to_string()), expected, "{:?}", raw);
+        }
+    }
+
+    #[test]
+    fn blink() {
+        let raw = "\u{1b}[5mslow\u{1b}[6mrapid\u{1b}[25m";
+        let (content, ansis) = extract_ansi(raw.to_string());
+        assert_eq!(content, "slowrapid");
+        assert_eq!(
+            ansis,
+            HashMap::from([
+                (0, vec![ANSISequence::Blink]),
+                (4, vec![ANSISequence::Blink]),
+                (9, vec![ANSISequence::NotBlink]),
+            ])
+        );
+    }
+
+    #[test]
+    fn reverse() {
+        let raw = "\u{1b}[7mreverse\u{1b}[27m";
+        let got = extract_ansi(raw.to_string());
+        let want = (
+            String::from("reverse"),
+            HashMap::from([
+                (0, vec![ANSISequence::Reverse]),
+                (7, vec![ANSISequence::NotReverse]),
+            ]),
+        );
+        assert_eq!(want.0, got.0);
+        assert_eq!(want.1, got.1);
+    }
+
     #[test]
     fn color_4bit_fg() {
         let raw = "\u{1b}[30m\u{1b}[31m\u{1b}[32m\u{1b}[33m\u{1b}[34m\u{1b}[35m\u{1b}[36m\u{1b}[37m4bit-colors\u{1b}[39m";
@@ -215,14 +604,14 @@ mod tests {
                 (
                     0,
                     vec![
-                        ANSISequence::SetFG8(0),
-                        ANSISequence::SetFG8(1),
-                        ANSISequence::SetFG8(2),
-                        ANSISequence::SetFG8(3),
-                        ANSISequence::SetFG8(4),
-                        ANSISequence::SetFG8(5),
-                        ANSISequence::SetFG8(6),
-                        ANSISequence::SetFG8(7),
+                        ANSISequence::SetFG4(0),
+                        ANSISequence::SetFG4(1),
+                        ANSISequence::SetFG4(2),
+                        ANSISequence::SetFG4(3),
+                        ANSISequence::SetFG4(4),
+                        ANSISequence::SetFG4(5),
+                        ANSISequence::SetFG4(6),
+                        ANSISequence::SetFG4(7),
                     ],
                 ),
                 (11, vec![ANSISequence::DefaultFG]),
@@ -242,14 +631,14 @@ mod tests {
                 (
                     0,
                     vec![
-                        ANSISequence::SetBG8(0),
-                        ANSISequence::SetBG8(1),
-                        ANSISequence::SetBG8(2),
-                        ANSISequence::SetBG8(3),
-                        ANSISequence::SetBG8(4),
-                        ANSISequence::SetBG8(5),
-                        ANSISequence::SetBG8(6),
-                        ANSISequence::SetBG8(7),
+                        ANSISequence::SetBG4(0),
+                        ANSISequence::SetBG4(1),
+                        ANSISequence::SetBG4(2),
+                        ANSISequence::SetBG4(3),
+                        ANSISequence::SetBG4(4),
+                        ANSISequence::SetBG4(5),
+                        ANSISequence::SetBG4(6),
+                        ANSISequence::SetBG4(7),
                     ],
                 ),
                 (11, vec![ANSISequence::DefaultBG]),
@@ -270,14 +659,14 @@ mod tests {
                 (
                     0,
                     vec![
-                        ANSISequence::SetFG8(8),
-                        ANSISequence::SetFG8(9),
-                        ANSISequence::SetFG8(10),
-                        ANSISequence::SetFG8(11),
-                        ANSISequence::SetFG8(12),
-                        ANSISequence::SetFG8(13),
-                        ANSISequence::SetFG8(14),
-                        ANSISequence::SetFG8(15),
+                        ANSISequence::SetFG4(8),
+                        ANSISequence::SetFG4(9),
+                        ANSISequence::SetFG4(10),
+                        ANSISequence::SetFG4(11),
+                        ANSISequence::SetFG4(12),
+                        ANSISequence::SetFG4(13),
+                        ANSISequence::SetFG4(14),
+                        ANSISequence::SetFG4(15),
                     ],
                 ),
                 (26, vec![ANSISequence::DefaultFG]),
@@ -298,14 +687,14 @@ mod tests {
                 (
                     0,
                     vec![
-                        ANSISequence::SetBG8(8),
-                        ANSISequence::SetBG8(9),
-                        ANSISequence::SetBG8(10),
-                        ANSISequence::SetBG8(11),
-                        ANSISequence::SetBG8(12),
-                        ANSISequence::SetBG8(13),
-                        ANSISequence::SetBG8(14),
-                        ANSISequence::SetBG8(15),
+                        ANSISequence::SetBG4(8),
+                        ANSISequence::SetBG4(9),
+                        ANSISequence::SetBG4(10),
+                        ANSISequence::SetBG4(11),
+                        ANSISequence::SetBG4(12),
+                        ANSISequence::SetBG4(13),
+                        ANSISequence::SetBG4(14),
+                        ANSISequence::SetBG4(15),
                     ],
                 ),
                 (26, vec![ANSISequence::DefaultBG]),
@@ -348,6 +737,28 @@ mod tests {
         assert_eq!(want.1, got.1);
     }
 
+    #[test]
+    fn color_8bit_underline() {
+        let raw = "\u{1b}[4;58;5;111m8-bit\u{1b}[59m";
+        let got = extract_ansi(raw.to_string());
+        let want = (
+            String::from("8-bit"),
+            HashMap::from([
+                (
+                    0,
+                    vec![
+                        ANSISequence::Underline,
+                        ANSISequence::SetUnderlineColor8(111),
+                    ],
+                ),
+                (5, vec![ANSISequence::DefaultUnderlineColor]),
+            ]),
+        );
+
+        assert_eq!(want.0, got.0);
+        assert_eq!(want.1, got.1);
+    }
+
     #[test]
     fn color_8bit_invalid() {
         let raw = "\u{1b}[38;5;256m\u{1b}[48;5;256minvalid";
@@ -356,6 +767,26 @@ mod tests {
         assert!(got.1.is_empty());
     }
 
+    #[test]
+    fn color_24bit_underline() {
+        let raw = "\u{1b}[58;2;100;110;111m24-bit\u{1b}[0m";
+        let got = extract_ansi(raw.to_string());
+        let want = (
+            String::from("24-bit"),
+            HashMap::from([
+                (0, vec![ANSISequence::SetUnderlineColor24(100, 110, 111)]),
+                (6, vec![ANSISequence::Reset]),
+            ]),
+        );
+
+        assert_eq!(want.0, got.0);
+        assert_eq!(want.1, got.1);
+
+        // 58;n isn't a valid underline color either
+        let raw = "\u{1b}[58;9mfoo";
+        assert_eq!(extract_ansi(raw.to_string()).0, raw);
+    }
+
     #[test]
     fn color_24bit_fg() {
         let raw = "\u{1b}[38;2;100;110;111m24-bit\u{1b}[0m";
@@ -404,6 +835,187 @@ mod tests {
         assert!(got.1.is_empty());
     }
 
+    #[test]
+    fn no_escapes() {
+        // the string is moved through without being copied
+        let raw = "plain text, no escapes here".repeat(1000);
+        let ptr = raw.as_ptr();
+        let (content, ansis) = extract_ansi(raw);
+        assert_eq!(content.as_ptr(), ptr);
+        assert!(ansis.is_empty());
+        assert_eq!(ansis.capacity(), 0);
+
+        let (content, ansis) = extract_ansi("a\u{1b}[1mb".to_string());
+        assert_eq!(content, "ab");
+        assert_eq!(ansis, HashMap::from([(1, vec![ANSISequence::Bold])]));
+    }
+
+    #[test]
+    fn csi_8bit() {
+        let got = extract_ansi("\u{9b}31mred\u{9b}0m".to_string());
+        let want = extract_ansi("\u{1b}[31mred\u{1b}[0m".to_string());
+        assert_eq!(got.0, "red");
+        assert_eq!(want.0, got.0);
+        assert_eq!(want.1, got.1);
+
+        let raw = "\u{9b}1337mnot a sequence\u{9b}";
+        let got = extract_ansi(raw.to_string());
+        assert_eq!(raw, got.0);
+        assert!(got.1.is_empty());
+    }
+
+    #[test]
+    fn private_mode() {
+        // cursor hide/show around a spinner, the styles in between are kept
+        let (content, ansis) =
+            extract_ansi("\u{1b}[?25l\u{1b}[1mbuilding\u{1b}[0m\u{1b}[?25h".to_string());
+        assert_eq!(content, "building");
+        assert_eq!(
+            ansis,
+            HashMap::from([
+                (0, vec![ANSISequence::Bold]),
+                (8, vec![ANSISequence::Reset])
+            ])
+        );
+
+        let (content, _) = extract_ansi("\u{9b}?2004hpaste mode\u{1b}[?1049l".to_string());
+        assert_eq!(content, "paste mode");
+
+        // unterminated ones are left as text
+        let raw = "foo \u{1b}[?25";
+        assert_eq!(extract_ansi(raw.to_string()).0, raw);
+
+        // and survive a carriage return replay until extract_ansi drops them
+        let raw = "\u{1b}[?25l- 1/2\r- 2/2";
+        let (content, _) = extract_ansi(apply_erase_in_line(raw.to_string()));
+        assert_eq!(content, "- 2/2");
+    }
+
+    #[test]
+    fn invalid_tail() {
+        let raw = "\u{1b}[1;38;5;300mfoo";
+        let got = extract_ansi(raw.to_string());
+        let want = (
+            String::from("foo"),
+            HashMap::from([(0, vec![ANSISequence::Bold])]),
+        );
+
+        assert_eq!(want.0, got.0);
+        assert_eq!(want.1, got.1);
+    }
+
+    #[test]
+    fn extended_color() {
+        let cases = [
+            // seq.get returns Option<&u8>, the 5 and 2 selectors still match through default
+            // binding modes. Index 0 and all-zero rgb are the edges most likely to be mismatched
+            (
+                "\u{1b}[38;5;0mfoo",
+                "foo",
+                HashMap::from([(0, vec![ANSISequence::SetFG8(0)])]),
+            ),
+            (
+                "\u{1b}[48;5;0mfoo",
+                "foo",
+                HashMap::from([(0, vec![ANSISequence::SetBG8(0)])]),
+            ),
+            (
+                "\u{1b}[48;2;0;0;0mfoo",
+                "foo",
+                HashMap::from([(0, vec![ANSISequence::SetBG24(0, 0, 0)])]),
+            ),
+            (
+                "\u{1b}[38;2;0;0;0;1mfoo",
+                "foo",
+                HashMap::from([(0, vec![ANSISequence::SetFG24(0, 0, 0), ANSISequence::Bold])]),
+            ),
+            // 38;5;0 isn't confused with the 4-bit black of 30
+            (
+                "\u{1b}[30;38;5;0mfoo",
+                "foo",
+                HashMap::from([(0, vec![ANSISequence::SetFG4(0), ANSISequence::SetFG8(0)])]),
+            ),
+            (
+                "\u{1b}[1;38;5;9mfoo",
+                "foo",
+                HashMap::from([(0, vec![ANSISequence::Bold, ANSISequence::SetFG8(9)])]),
+            ),
+            (
+                "\u{1b}[48;5;9;1mfoo",
+                "foo",
+                HashMap::from([(0, vec![ANSISequence::SetBG8(9), ANSISequence::Bold])]),
+            ),
+            // 38;n isn't a valid extended color, so the whole sequence leaks as text
+            ("\u{1b}[38;9mfoo", "\u{1b}[38;9mfoo", HashMap::new()),
+            // but it doesn't affect a preceding valid sequence
+            (
+                "\u{1b}[1m\u{1b}[38;9mfoo",
+                "\u{1b}[38;9mfoo",
+                HashMap::from([(0, vec![ANSISequence::Bold])]),
+            ),
+            // or the valid params before it in the same sequence
+            (
+                "\u{1b}[1;38;9mfoo",
+                "foo",
+                HashMap::from([(0, vec![ANSISequence::Bold])]),
+            ),
+        ];
+
+        for (raw, content, ansis) in cases {
+            let got = extract_ansi(raw.to_string());
+            assert_eq!(got.0, content, "{:?}", raw);
+            assert_eq!(got.1, ansis, "{:?}", raw);
+        }
+    }
+
+    #[test]
+    fn to_escape() {
+        assert_eq!(ANSISequence::Bold.to_escape(), "\u{1b}[1m");
+        assert_eq!(ANSISequence::SetFG4(1).to_escape(), "\u{1b}[31m");
+        assert_eq!(ANSISequence::SetBG4(9).to_escape(), "\u{1b}[101m");
+        assert_eq!(ANSISequence::SetFG8(1).to_escape(), "\u{1b}[38;5;1m");
+        assert_eq!(ANSISequence::SetBG8(200).to_escape(), "\u{1b}[48;5;200m");
+        assert_eq!(
+            ANSISequence::SetFG24(1, 2, 3).to_escape(),
+            "\u{1b}[38;2;1;2;3m"
+        );
+
+        // every variant parses back into itself
+        let all = [
+            ANSISequence::Reset,
+            ANSISequence::Bold,
+            ANSISequence::Dim,
+            ANSISequence::Italic,
+            ANSISequence::Underline,
+            ANSISequence::NotBold,
+            ANSISequence::NotItalic,
+            ANSISequence::NotUnderline,
+            ANSISequence::Reverse,
+            ANSISequence::NotReverse,
+            ANSISequence::Conceal,
+            ANSISequence::NotConceal,
+            ANSISequence::Blink,
+            ANSISequence::NotBlink,
+            ANSISequence::SetFG4(1),
+            ANSISequence::SetFG4(9),
+            ANSISequence::SetFG8(9),
+            ANSISequence::DefaultFG,
+            ANSISequence::SetBG4(1),
+            ANSISequence::SetBG4(9),
+            ANSISequence::SetBG8(9),
+            ANSISequence::DefaultBG,
+            ANSISequence::SetFG24(1, 2, 3),
+            ANSISequence::SetBG24(1, 2, 3),
+            ANSISequence::SetUnderlineColor8(9),
+            ANSISequence::SetUnderlineColor24(1, 2, 3),
+            ANSISequence::DefaultUnderlineColor,
+        ];
+        for seq in all {
+            let (_, ansis) = extract_ansi(seq.to_escape());
+            assert_eq!(ansis, HashMap::from([(0, vec![seq.clone()])]), "{:?}", seq);
+        }
+    }
+
     #[test]
     fn multi_seq() {
         let raw = "\u{1b}[36;1mbold cyan\u{1b}[0m";
@@ -411,7 +1023,7 @@ mod tests {
         let want = (
             String::from("bold cyan"),
             HashMap::from([
-                (0, vec![ANSISequence::SetFG8(6), ANSISequence::Bold]),
+                (0, vec![ANSISequence::SetFG4(6), ANSISequence::Bold]),
                 (9, vec![ANSISequence::Reset]),
             ]),
         );
@@ -419,4 +1031,32 @@ mod tests {
         assert_eq!(want.0, got.0);
         assert_eq!(want.1, got.1);
     }
+
+    #[test]
+    fn separate_seqs_same_index() {
+        // separate escapes at the same index keep their source order, since applying a reset before
+        // or after a color gives different styles
+        let cases = [
+            (
+                "a\u{1b}[31m\u{1b}[0mb",
+                vec![ANSISequence::SetFG4(1), ANSISequence::Reset],
+                None,
+            ),
+            (
+                "a\u{1b}[0m\u{1b}[31mb",
+                vec![ANSISequence::Reset, ANSISequence::SetFG4(1)],
+                Some(Color::Bit4(1)),
+            ),
+        ];
+
+        for (raw, seqs, fg) in cases {
+            let (content, ansis) = extract_ansi(raw.to_string());
+            assert_eq!(content, "ab");
+            assert_eq!(ansis, HashMap::from([(1, seqs)]));
+
+            let mut styles = Styles::new();
+            styles.apply_ansis(&ansis[&1]);
+            assert_eq!(styles.fg, fg, "{:?}", raw);
+        }
+    }
 }
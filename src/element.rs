@@ -1,5 +1,6 @@
-use crate::line::Line;
+use crate::line::{parse_source_location, Line, LinkKind};
 use crate::style::Styles;
+use serde::de::{Deserialize, Deserializer};
 use serde::ser::{Serialize, SerializeStruct, Serializer};
 
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
@@ -8,6 +9,139 @@ pub enum Element {
     Text(String, Styles),
     // Link(href, children)
     Link(String, Vec<Element>),
+    // a path:line[:col] source location that a frontend can jump to
+    SourceRef {
+        path: String,
+        line: usize,
+        col: Option<usize>,
+        children: Vec<Element>,
+    },
+}
+
+impl Element {
+    // the visible text of the element, ignoring styles and hrefs. Concealed text is blanked out
+    pub fn plain_text(&self) -> String {
+        match self {
+            Element::Text(content, styles) if styles.conceal => {
+                content.chars().map(|_| ' ').collect()
+            }
+            Element::Text(content, _) => content.clone(),
+            Element::Link(_, children) | Element::SourceRef { children, .. } => {
+                plain_text(children)
+            }
+        }
+    }
+
+    // what kind of link the element is, emails are linked as mailto: hrefs. None for text
+    pub fn link_kind(&self) -> Option<LinkKind> {
+        match self {
+            Element::Text(..) => None,
+            Element::Link(href, _) if href.starts_with("mailto:") => Some(LinkKind::Email),
+            Element::Link(..) => Some(LinkKind::Url),
+            Element::SourceRef { .. } => Some(LinkKind::Source),
+        }
+    }
+
+    // whether the element is or contains highlighted text
+    pub fn has_match(&self) -> bool {
+        match self {
+            Element::Text(_, styles) => styles.highlight,
+            Element::Link(_, children) | Element::SourceRef { children, .. } => {
+                children.iter().any(Element::has_match)
+            }
+        }
+    }
+}
+
+pub fn plain_text(elements: &[Element]) -> String {
+    elements.iter().map(Element::plain_text).collect()
+}
+
+// soft-wraps elements into rows of at most width visible chars, breaking at the last space that fits
+// (the space itself is dropped) or hard splitting words longer than width. Styles carry across rows
+// and links split across rows keep their href on each piece. A width of 0 disables wrapping
+pub fn wrap(elements: &[Element], width: usize) -> Vec<Vec<Element>> {
+    // every visible char with its styles and the link (if any) it belongs to
+    let mut cells: Vec<(char, &Styles, Option<&Element>)> = Vec::new();
+    for element in elements {
+        match element {
+            Element::Text(content, styles) => {
+                cells.extend(content.chars().map(|ch| (ch, styles, None)));
+            }
+            Element::Link(_, children) | Element::SourceRef { children, .. } => {
+                for child in children {
+                    if let Element::Text(content, styles) = child {
+                        cells.extend(content.chars().map(|ch| (ch, styles, Some(element))));
+                    }
+                }
+            }
+        }
+    }
+
+    let mut rows = Vec::new();
+    let mut start = 0;
+    while width > 0 && cells.len() - start > width {
+        let limit = start + width;
+        let (end, next) = if cells[limit].0 == ' ' {
+            (limit, limit + 1)
+        } else {
+            match (start + 1..limit).rev().find(|&i| cells[i].0 == ' ') {
+                Some(space) => (space, space + 1),
+                None => (limit, limit),
+            }
+        };
+
+        rows.push(row_elements(&cells[start..end]));
+        start = next;
+    }
+
+    rows.push(row_elements(&cells[start..]));
+    rows
+}
+
+fn row_elements(cells: &[(char, &Styles, Option<&Element>)]) -> Vec<Element> {
+    let mut elements: Vec<Element> = Vec::new();
+    let mut current_link: Option<&Element> = None;
+
+    for (ch, styles, link) in cells {
+        let same_link = match (current_link, link) {
+            (Some(current), Some(link)) => std::ptr::eq(current, *link),
+            (None, None) => true,
+            _ => false,
+        };
+
+        if !same_link {
+            if let Some(link) = link {
+                elements.push(match link {
+                    Element::Link(href, _) => Element::Link(href.clone(), Vec::new()),
+                    Element::SourceRef {
+                        path, line, col, ..
+                    } => Element::SourceRef {
+                        path: path.clone(),
+                        line: *line,
+                        col: *col,
+                        children: Vec::new(),
+                    },
+                    Element::Text(..) => unreachable!("links only contain text"),
+                });
+            }
+            current_link = *link;
+        }
+
+        let target = match (link, elements.last_mut()) {
+            (Some(_), Some(Element::Link(_, children) | Element::SourceRef { children, .. })) => {
+                children
+            }
+            _ => &mut elements,
+        };
+
+        match target.last_mut() {
+            Some(Element::Text(content, last)) if last == *styles => content.push(*ch),
+            _ => target.push(Element::Text(ch.to_string(), (*styles).clone())),
+        }
+    }
+
+    elements
 }
 
 impl Serialize for Element {
@@ -16,27 +150,103 @@ impl Serialize for Element {
         S: Serializer,
     {
         match self {
+            // objects are tagged with a "type" of text, link or sourceref for typescript unions
             Element::Text(content, styles) => {
                 // if there are no styles, just serialize the content as string to reduce size
                 if styles.is_empty() {
                     return serializer.serialize_str(content);
                 }
 
-                let mut state = serializer.serialize_struct("Text", 2)?;
+                let mut state = serializer.serialize_struct("Text", 3)?;
+                state.serialize_field("type", "text")?;
                 state.serialize_field("content", content)?;
                 state.serialize_field("styles", styles)?;
                 state.end()
             }
             Element::Link(href, children) => {
-                let mut state = serializer.serialize_struct("Link", 2)?;
+                let mut state = serializer.serialize_struct("Link", 5)?;
+                state.serialize_field("type", "link")?;
                 state.serialize_field("href", href)?;
                 state.serialize_field("children", children)?;
+                // so consumers can pick an icon without parsing the href
+                if let Some(kind) = self.link_kind() {
+                    state.serialize_field("kind", kind.name())?;
+                }
+                // lets consumers find links with search hits without scanning the children
+                if self.has_match() {
+                    state.serialize_field("has_match", &true)?;
+                } else {
+                    state.skip_field("has_match")?;
+                }
+                state.end()
+            }
+            Element::SourceRef {
+                path,
+                line,
+                col,
+                children,
+            } => {
+                let mut state = serializer.serialize_struct("SourceRef", 5)?;
+                state.serialize_field("type", "sourceref")?;
+                state.serialize_field("path", path)?;
+                state.serialize_field("line", line)?;
+                match col {
+                    Some(col) => state.serialize_field("col", col)?,
+                    None => state.skip_field("col")?,
+                }
+                state.serialize_field("children", children)?;
                 state.end()
             }
         }
     }
 }
 
+impl<'de> Deserialize<'de> for Element {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        // the shapes written by serialize, has_match is derived from the children so it's ignored
+        #[derive(serde::Deserialize)]
+        #[serde(untagged)]
+        enum Repr {
+            Plain(String),
+            Text {
+                content: String,
+                styles: Styles,
+            },
+            Link {
+                href: String,
+                children: Vec<Element>,
+            },
+            SourceRef {
+                path: String,
+                line: usize,
+                #[serde(default)]
+                col: Option<usize>,
+                children: Vec<Element>,
+            },
+        }
+
+        Ok(match Repr::deserialize(deserializer)? {
+            Repr::Plain(content) => Element::Text(content, Styles::new()),
+            Repr::Text { content, styles } => Element::Text(content, styles),
+            Repr::Link { href, children } => Element::Link(href, children),
+            Repr::SourceRef {
+                path,
+                line,
+                col,
+                children,
+            } => Element::SourceRef {
+                path,
+                line,
+                col,
+                children,
+            },
+        })
+    }
+}
+
 // Builder contructs renderable elements from a line
 struct Builder {
     // output elements
@@ -53,6 +263,10 @@ struct Builder {
     end_link_idx: Option<usize>,
     // if currently within a link, the href of the link
     link_href: Option<String>,
+    // if currently within a link, what kind of link it is
+    link_kind: Option<LinkKind>,
+    // whether text outside of highlights is dimmed
+    dim_unmatched: bool,
 }
 
 impl Builder {
@@ -65,20 +279,23 @@ impl Builder {
             end_highlight_idx: None,
             end_link_idx: None,
             link_href: None,
+            link_kind: None,
+            dim_unmatched: false,
         }
     }
 
     pub fn elements_from(&mut self, line: &Line) {
+        self.styles = line.start_styles.clone();
+        if line.level_fg.is_some() {
+            self.styles.fg = line.level_fg.clone();
+        }
+        self.dim_unmatched = line.dim_unmatched;
+
         for (i, ch) in line.content.char_indices() {
             let mut new_styles = self.styles.clone();
 
-            // starting a link
-            if let Some(end_idx) = line.links.get(&i) {
-                self.flush();
-                self.start_link(*end_idx, line.content[i..*end_idx].to_string());
-            }
-
-            // ending a link
+            // ending a link, before starting one so a link that begins where another ends isn't
+            // merged into it
             if let Some(end_idx) = self.end_link_idx {
                 if i == end_idx {
                     self.flush();
@@ -86,9 +303,21 @@ impl Builder {
                 }
             }
 
+            // starting a link
+            if let Some((end_idx, kind)) = line.links.get(&i) {
+                self.flush();
+                let text = &line.content[i..*end_idx];
+                let href = match kind {
+                    LinkKind::Url | LinkKind::Source => text.to_string(),
+                    LinkKind::Email => format!("mailto:{}", text),
+                };
+                self.start_link(*end_idx, href, *kind);
+            }
+
             // starting a highlight
             if let Some(end_idx) = line.highlights.get(&i) {
                 new_styles.highlight = true;
+                new_styles.highlight_group = line.highlight_groups.get(&i).copied();
                 self.end_highlight_idx = Some(*end_idx);
             }
 
@@ -96,6 +325,7 @@ impl Builder {
             if let Some(end_idx) = self.end_highlight_idx {
                 if i == end_idx {
                     new_styles.highlight = false;
+                    new_styles.highlight_group = None;
                     self.end_highlight_idx = None;
                 }
             }
@@ -118,6 +348,12 @@ impl Builder {
         if self.is_in_link() {
             self.end_link();
         }
+
+        if line.truncated {
+            self.styles = Styles::new();
+            self.text = format!("… ({} more chars)", line.truncated_chars);
+            self.flush();
+        }
     }
 
     // appends a new element with the current text accumulator and styles if text is not empty
@@ -126,13 +362,25 @@ impl Builder {
             return;
         }
 
-        let element = Element::Text(self.text.clone(), self.styles.clone());
+        let mut styles = self.styles.clone();
+        if self.dim_unmatched && !styles.highlight {
+            styles.dim = true;
+        }
 
-        if self.is_in_link() {
-            self.link_elements.push(element);
+        let elements = if self.is_in_link() {
+            &mut self.link_elements
         } else {
-            self.elements.push(element);
+            &mut self.elements
+        };
+
+        // coalesce with the previous element if it has the same styles
+        match elements.last_mut() {
+            Some(Element::Text(content, last)) if *last == styles => {
+                content.push_str(&self.text);
+            }
+            _ => elements.push(Element::Text(self.text.clone(), styles)),
         }
+
         self.text.clear();
     }
 
@@ -140,17 +388,28 @@ impl Builder {
         self.end_link_idx.is_some()
     }
 
-    fn start_link(&mut self, end_idx: usize, href: String) {
+    fn start_link(&mut self, end_idx: usize, href: String, kind: LinkKind) {
         self.end_link_idx = Some(end_idx);
         self.link_href = Some(href);
+        self.link_kind = Some(kind);
     }
 
     fn end_link(&mut self) {
-        let link = Element::Link(self.link_href.clone().unwrap(), self.link_elements.clone());
+        let href = self.link_href.take().unwrap();
+        let children = std::mem::take(&mut self.link_elements);
+
+        let link = match (self.link_kind.take(), parse_source_location(&href)) {
+            (Some(LinkKind::Source), Some((path, line, col))) => Element::SourceRef {
+                path: path.to_string(),
+                line,
+                col,
+                children,
+            },
+            _ => Element::Link(href, children),
+        };
+
         self.elements.push(link);
-        self.link_elements.clear();
         self.end_link_idx = None;
-        self.link_href = None;
     }
 }
 
@@ -163,7 +422,9 @@ pub fn build_elements(line: &Line) -> Vec<Element> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::line::ParseOptions;
     use crate::style::Color;
+    use std::collections::HashMap;
 
     #[test]
     fn simple() {
@@ -175,6 +436,44 @@ mod tests {
         assert_eq!(elements, expected);
     }
 
+    #[test]
+    fn deserialize() {
+        let mut line = Line::with_options(
+            1,
+            None,
+            "\u{1b}[1mbold\u{1b}[0m src/main.rs:4:2 https://reb.gg",
+            &ParseOptions {
+                detect_source_locations: true,
+                ..ParseOptions::default()
+            },
+        );
+        line.highlight("reb");
+
+        let json = serde_json::to_string(&line.elements).unwrap();
+        let elements: Vec<Element> = serde_json::from_str(&json).unwrap();
+        assert_eq!(elements, line.elements);
+    }
+
+    #[test]
+    fn serialize_tagged() {
+        let mut line = Line::from("\u{1b}[1mbold\u{1b}[0m https://reb.gg");
+        line.highlight("reb");
+
+        let json: Vec<String> = line
+            .elements
+            .iter()
+            .map(|element| serde_json::to_string(element).unwrap())
+            .collect();
+        assert_eq!(
+            json,
+            vec![
+                r#"{"type":"text","content":"bold","styles":{"b":true}}"#,
+                r#"" ""#,
+                r#"{"type":"link","href":"https://reb.gg","children":["https://",{"type":"text","content":"reb","styles":{"hl":true}},".gg"],"kind":"url","has_match":true}"#,
+            ]
+        );
+    }
+
     #[test]
     fn link() {
         let line = Line::from("foo https://reb.gg bar");
@@ -192,6 +491,43 @@ mod tests {
         assert_eq!(elements, expected);
     }
 
+    #[test]
+    fn email() {
+        let line = Line::with_options(
+            0,
+            None,
+            "https://reb.gg by foo@reb.gg",
+            &ParseOptions {
+                detect_emails: true,
+                ..ParseOptions::default()
+            },
+        );
+        let elements = build_elements(&line);
+
+        let expected = vec![
+            Element::Link(
+                "https://reb.gg".to_string(),
+                vec![Element::Text("https://reb.gg".to_string(), Styles::new())],
+            ),
+            Element::Text(" by ".to_string(), Styles::new()),
+            Element::Link(
+                "mailto:foo@reb.gg".to_string(),
+                vec![Element::Text("foo@reb.gg".to_string(), Styles::new())],
+            ),
+        ];
+
+        assert_eq!(elements, expected);
+
+        let kinds: Vec<_> = elements.iter().map(Element::link_kind).collect();
+        assert_eq!(
+            kinds,
+            vec![Some(LinkKind::Url), None, Some(LinkKind::Email)]
+        );
+        let json: serde_json::Value = serde_json::to_value(&elements).unwrap();
+        assert_eq!(json[0]["kind"], "url");
+        assert_eq!(json[2]["kind"], "email");
+    }
+
     #[test]
     fn ends_with_link() {
         let line = Line::from("foo https://reb.gg");
@@ -208,6 +544,53 @@ mod tests {
         assert_eq!(elements, expected);
     }
 
+    #[test]
+    fn coalesce() {
+        // faint text and the text after it only differ until everything outside matches is
+        // dimmed, then they're a single run
+        let mut line = Line::from("\u{1b}[2mfaint\u{1b}[22m plain \u{1b}[1mbold");
+        line.dim_unmatched = true;
+        let elements = build_elements(&line);
+
+        let dim = Styles {
+            dim: true,
+            ..Styles::new()
+        };
+        let expected = vec![
+            Element::Text("faint plain ".to_string(), dim.clone()),
+            Element::Text("bold".to_string(), Styles { bold: true, ..dim }),
+        ];
+
+        assert_eq!(elements, expected);
+    }
+
+    #[test]
+    fn truncated() {
+        let line = Line::with_options(
+            0,
+            None,
+            "\u{1b}[1mfoo bar baz",
+            &ParseOptions {
+                max_line_length: 3,
+                ..ParseOptions::default()
+            },
+        );
+        let elements = build_elements(&line);
+
+        let expected = vec![
+            Element::Text(
+                "foo".to_string(),
+                Styles {
+                    bold: true,
+                    ..Styles::new()
+                },
+            ),
+            Element::Text("… (8 more chars)".to_string(), Styles::new()),
+        ];
+
+        assert_eq!(elements, expected);
+    }
+
     #[test]
     fn highlight() {
         let mut line = Line::from("foo bar");
@@ -237,7 +620,7 @@ mod tests {
         let expected = vec![Element::Text(
             "bold cyan".to_string(),
             Styles {
-                fg: Some(Color::Bit8(6)),
+                fg: Some(Color::Bit4(6)),
                 bold: true,
                 ..Styles::new()
             },
@@ -246,6 +629,72 @@ mod tests {
         assert_eq!(elements, expected);
     }
 
+    #[test]
+    fn style_churn() {
+        // sequences that cancel each other out before any text don't leave empty elements behind,
+        // and the text gets the final styles
+        let line = Line::from(
+            "\u{1b}[1m\u{1b}[0m\u{1b}[31m\u{1b}[3mfoo\u{1b}[4m\u{1b}[0m\u{1b}[32m\u{1b}[0mbar",
+        );
+        let elements = build_elements(&line);
+
+        let expected = vec![
+            Element::Text(
+                "foo".to_string(),
+                Styles {
+                    italic: true,
+                    fg: Some(Color::Bit4(1)),
+                    ..Styles::new()
+                },
+            ),
+            Element::Text("bar".to_string(), Styles::new()),
+        ];
+
+        assert_eq!(elements, expected);
+    }
+
+    #[test]
+    fn empty_reset() {
+        let line = Line::from("\u{1b}[31mred\u{1b}[mfoo");
+        let elements = build_elements(&line);
+
+        let expected = vec![
+            Element::Text(
+                "red".to_string(),
+                Styles {
+                    fg: Some(Color::Bit4(1)),
+                    ..Styles::new()
+                },
+            ),
+            Element::Text("foo".to_string(), Styles::new()),
+        ];
+
+        assert_eq!(elements, expected);
+    }
+
+    #[test]
+    fn same_index_order() {
+        let line = Line::from("\u{1b}[31m\u{1b}[0mfoo");
+        let elements = build_elements(&line);
+        assert_eq!(
+            elements,
+            vec![Element::Text("foo".to_string(), Styles::new())]
+        );
+
+        let line = Line::from("\u{1b}[0m\u{1b}[31mfoo");
+        let elements = build_elements(&line);
+        assert_eq!(
+            elements,
+            vec![Element::Text(
+                "foo".to_string(),
+                Styles {
+                    fg: Some(Color::Bit4(1)),
+                    ..Styles::new()
+                }
+            )]
+        );
+    }
+
     #[test]
     fn mixed() {
         let mut line = Line::from("do re me https://\u{1b}[31mreb.gg\u{1b}[0m fa la ti do");
@@ -269,7 +718,7 @@ mod tests {
                     Element::Text(
                         "re".to_string(),
                         Styles {
-                            fg: Some(Color::Bit8(1)),
+                            fg: Some(Color::Bit4(1)),
                             highlight: true,
                             ..Styles::new()
                         },
@@ -277,7 +726,7 @@ mod tests {
                     Element::Text(
                         "b.gg".to_string(),
                         Styles {
-                            fg: Some(Color::Bit8(1)),
+                            fg: Some(Color::Bit4(1)),
                             ..Styles::new()
                         },
                     ),
@@ -287,5 +736,186 @@ mod tests {
         ];
 
         assert_eq!(elements, expected);
+
+        assert!(elements[3].has_match());
+        assert!(!elements[2].has_match());
+        assert_eq!(
+            serde_json::to_string(&elements[3]).unwrap(),
+            r#"{"type":"link","href":"https://reb.gg","children":["https://",{"type":"text","content":"re","styles":{"hl":true,"fg":1,"f4":true}},{"type":"text","content":"b.gg","styles":{"fg":1,"f4":true}}],"kind":"url","has_match":true}"#
+        );
+        assert_eq!(
+            serde_json::to_string(&build_elements(&Line::from("https://reb.gg"))).unwrap(),
+            r#"[{"type":"link","href":"https://reb.gg","children":["https://reb.gg"],"kind":"url"}]"#
+        );
+    }
+
+    #[test]
+    fn plain_text() {
+        let mut line = Line::from("do re me https://\u{1b}[31mreb.gg\u{1b}[0m fa la ti do");
+        line.highlight("re");
+        let elements = build_elements(&line);
+
+        assert_eq!(super::plain_text(&elements), line.content);
+        assert_eq!(elements[3].plain_text(), "https://reb.gg");
+    }
+
+    #[test]
+    fn conceal() {
+        let mut line = Line::from("password: \u{1b}[8mhunter2\u{1b}[28m!");
+        assert_eq!(super::plain_text(&line.elements), "password:        !");
+
+        // concealed text is still searchable
+        line.highlight("hunter");
+        assert_eq!(line.highlights, HashMap::from([(10, 16)]));
+        assert_eq!(
+            line.elements[1],
+            Element::Text(
+                "hunter".to_string(),
+                Styles {
+                    conceal: true,
+                    highlight: true,
+                    ..Styles::new()
+                }
+            )
+        );
+    }
+
+    #[test]
+    fn blink() {
+        let line = Line::from("\u{1b}[5mfoo\u{1b}[25m");
+        assert_eq!(
+            line.elements,
+            vec![Element::Text(
+                "foo".to_string(),
+                Styles {
+                    blink: true,
+                    ..Styles::new()
+                }
+            )]
+        );
+    }
+
+    #[test]
+    fn source_ref() {
+        let line = Line::with_options(
+            0,
+            None,
+            "src/main.rs:42:10: error",
+            &ParseOptions {
+                detect_source_locations: true,
+                ..ParseOptions::default()
+            },
+        );
+
+        let expected = vec![
+            Element::SourceRef {
+                path: "src/main.rs".to_string(),
+                line: 42,
+                col: Some(10),
+                children: vec![Element::Text(
+                    "src/main.rs:42:10".to_string(),
+                    Styles::new(),
+                )],
+            },
+            Element::Text(": error".to_string(), Styles::new()),
+        ];
+
+        assert_eq!(line.elements, expected);
+        assert_eq!(
+            serde_json::to_string(&line.elements[0]).unwrap(),
+            r#"{"type":"sourceref","path":"src/main.rs","line":42,"col":10,"children":["src/main.rs:42:10"]}"#
+        );
+    }
+
+    #[test]
+    fn highlight_ends_at_link() {
+        let mut line = Line::from("see:https://reb.gg");
+        line.highlight("see:");
+
+        let expected = vec![
+            Element::Text(
+                "see:".to_string(),
+                Styles {
+                    highlight: true,
+                    ..Styles::new()
+                },
+            ),
+            Element::Link(
+                "https://reb.gg".to_string(),
+                vec![Element::Text("https://reb.gg".to_string(), Styles::new())],
+            ),
+        ];
+
+        assert_eq!(line.elements, expected);
+    }
+
+    #[test]
+    fn adjacent_links() {
+        let mut line = Line::from("ab");
+        line.links = HashMap::from([(0, (1, LinkKind::Url)), (1, (2, LinkKind::Url))]);
+        let elements = build_elements(&line);
+
+        let expected = vec![
+            Element::Link(
+                "a".to_string(),
+                vec![Element::Text("a".to_string(), Styles::new())],
+            ),
+            Element::Link(
+                "b".to_string(),
+                vec![Element::Text("b".to_string(), Styles::new())],
+            ),
+        ];
+
+        assert_eq!(elements, expected);
+    }
+
+    #[test]
+    fn wrap_plain() {
+        let line = Line::from("the quick brown fox jumps");
+        let rows: Vec<String> = wrap(&line.elements, 10)
+            .iter()
+            .map(|row| super::plain_text(row))
+            .collect();
+        assert_eq!(rows, vec!["the quick", "brown fox", "jumps"]);
+
+        assert_eq!(wrap(&line.elements, 0), vec![line.elements.clone()]);
+        assert_eq!(wrap(&[], 10), vec![Vec::<Element>::new()]);
+    }
+
+    #[test]
+    fn wrap_styled() {
+        let line = Line::from("\u{1b}[1mabcdefghij\u{1b}[0mkl see https://reb.gg");
+        let bold = Styles {
+            bold: true,
+            ..Styles::new()
+        };
+
+        let expected = vec![
+            vec![Element::Text("abcd".to_string(), bold.clone())],
+            vec![Element::Text("efgh".to_string(), bold.clone())],
+            vec![
+                Element::Text("ij".to_string(), bold.clone()),
+                Element::Text("kl".to_string(), Styles::new()),
+            ],
+            vec![Element::Text("see".to_string(), Styles::new())],
+            vec![Element::Link(
+                "https://reb.gg".to_string(),
+                vec![Element::Text("http".to_string(), Styles::new())],
+            )],
+            vec![Element::Link(
+                "https://reb.gg".to_string(),
+                vec![Element::Text("s://".to_string(), Styles::new())],
+            )],
+            vec![Element::Link(
+                "https://reb.gg".to_string(),
+                vec![Element::Text("reb.".to_string(), Styles::new())],
+            )],
+            vec![Element::Link(
+                "https://reb.gg".to_string(),
+                vec![Element::Text("gg".to_string(), Styles::new())],
+            )],
+        ];
+
+        assert_eq!(line.word_wrap(4), expected);
     }
 }
@@ -0,0 +1,58 @@
+use std::fmt;
+
+#[derive(Debug, PartialEq)]
+pub enum Error {
+    InvalidTimestampFormat(String),
+    // the serde_json error message, native builds get the serde_json::Error itself
+    Serialize(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::InvalidTimestampFormat(fmt) => write!(f, "invalid timestamp format: {}", fmt),
+            Error::Serialize(msg) => write!(f, "failed to serialize: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Self {
+        Error::Serialize(err.to_string())
+    }
+}
+
+// the only serialization that can fail is writing it out, which is native only
+#[cfg(all(test, feature = "native"))]
+mod tests {
+    use super::*;
+    use crate::Parser;
+    use std::io;
+
+    // a writer that always fails, like a closed pipe
+    struct Closed;
+
+    impl io::Write for Closed {
+        fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+            Err(io::Error::new(io::ErrorKind::BrokenPipe, "pipe closed"))
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn serialize_message() {
+        let mut parser = Parser::new();
+        parser.set_raw("foo\nbar\n");
+
+        let err = parser.serialize_to_writer(Closed, false).unwrap_err();
+        assert_eq!(
+            Error::from(err).to_string(),
+            "failed to serialize: pipe closed"
+        );
+    }
+}
@@ -1,8 +1,13 @@
 mod ansi;
 mod element;
+mod error;
 mod line;
+mod markdown;
 mod parser;
 mod style;
+mod timestamp;
 
-pub use line::{Command, Group, Line};
-pub use parser::Parser;
+pub use error::Error;
+pub use line::{Command, Group, Line, LinkKind, ParseOptions};
+pub use parser::{Parser, Summary};
+pub use style::{bit8_to_rgb, Color};
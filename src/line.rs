@@ -2,13 +2,19 @@ use std::collections::HashMap;
 
 use chrono::{DateTime, Utc};
 use linkify::LinkFinder;
+use serde::de::{Deserializer, Error as _};
 use serde::ser::Serializer;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
 
-use crate::ansi::{extract_ansi, ANSISequence};
-use crate::element::{build_elements, Element};
+use crate::ansi::{apply_erase_in_line, extract_ansi, extract_ansi_truncated, ANSISequence};
+use crate::element::{build_elements, wrap, Element};
+use crate::style::{Color, Styles};
+use crate::timestamp::TimestampFormat;
 
 // https://docs.github.com/en/actions/using-workflows/workflow-commands-for-github-actions
+// commands are serialized as their numeric discriminant, these values are part of the wire format
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub enum Command {
     Command = 1,
@@ -20,6 +26,9 @@ pub enum Command {
     Warning = 7,
     Group = 8,
     EndGroup = 9,
+    // azure style "##[section]Starting: build" headers, they start a new top level line but don't
+    // group the lines after them
+    Section = 10,
 }
 
 impl Serialize for Command {
@@ -31,6 +40,16 @@ impl Serialize for Command {
     }
 }
 
+impl<'de> Deserialize<'de> for Command {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let value = u8::deserialize(deserializer)?;
+        Self::from_u8(value).ok_or_else(|| D::Error::custom(format!("unknown command: {}", value)))
+    }
+}
+
 impl Command {
     fn from(value: &str) -> Option<Self> {
         match value {
@@ -43,54 +62,281 @@ impl Command {
             "warning" => Some(Self::Warning),
             "group" => Some(Self::Group),
             "endgroup" => Some(Self::EndGroup),
+            "section" => Some(Self::Section),
+            _ => None,
+        }
+    }
+
+    // inverse of the numeric serialization
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            1 => Some(Self::Command),
+            2 => Some(Self::Debug),
+            3 => Some(Self::Error),
+            4 => Some(Self::Info),
+            5 => Some(Self::Notice),
+            6 => Some(Self::Verbose),
+            7 => Some(Self::Warning),
+            8 => Some(Self::Group),
+            9 => Some(Self::EndGroup),
+            10 => Some(Self::Section),
+            _ => None,
+        }
+    }
+
+    // default foreground for lines of this level when the log doesn't color them: red errors,
+    // yellow warnings, blue notices and magenta debug lines
+    pub fn level_color(&self) -> Option<Color> {
+        match self {
+            Self::Error => Some(Color::Bit4(1)),
+            Self::Warning => Some(Color::Bit4(3)),
+            Self::Notice => Some(Color::Bit4(4)),
+            Self::Debug => Some(Color::Bit4(5)),
             _ => None,
         }
     }
+
+    // infers a log level from common bare text prefixes, e.g. "ERROR: boom" or "[warn] careful"
+    fn infer(content: &str) -> Option<Self> {
+        const PREFIXES: [(&str, Command); 7] = [
+            ("error:", Command::Error),
+            ("err:", Command::Error),
+            ("warning:", Command::Warning),
+            ("warn:", Command::Warning),
+            ("[warn]", Command::Warning),
+            ("info:", Command::Info),
+            ("debug:", Command::Debug),
+        ];
+
+        let prefixed = PREFIXES
+            .iter()
+            .find(|(prefix, _)| {
+                content
+                    .get(..prefix.len())
+                    .is_some_and(|start| start.eq_ignore_ascii_case(prefix))
+            })
+            .map(|(_, cmd)| *cmd);
+
+        // lowercase bracketed levels like "[info]" are already parsed as commands, this catches
+        // the other cases, e.g. "[INFO]"
+        prefixed.or_else(|| {
+            let (name, _) = content.strip_prefix('[')?.split_once(']')?;
+            match Command::from(&name.to_ascii_lowercase())? {
+                cmd @ (Command::Error | Command::Warning | Command::Info | Command::Debug) => {
+                    Some(cmd)
+                }
+                _ => None,
+            }
+        })
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize)]
+pub enum LinkKind {
+    Url,
+    Email,
+    // a path:line[:col] source location, e.g. "src/main.rs:42:10"
+    Source,
+}
+
+impl LinkKind {
+    // lowercase name, as serialized in a link element's kind
+    pub fn name(&self) -> &'static str {
+        match self {
+            LinkKind::Url => "url",
+            LinkKind::Email => "email",
+            LinkKind::Source => "source",
+        }
+    }
+}
+
+// ParseOptions controls how raw lines are parsed
+#[derive(Debug, Clone, Default)]
+pub struct ParseOptions {
+    // also detect email addresses as links (with a mailto: href)
+    pub detect_emails: bool,
+    // if not empty, only urls with these schemes (e.g. "https", "ftp") are detected as links
+    pub link_schemes: Vec<String>,
+    // lines with more visible chars than this are truncated, 0 disables truncation
+    pub max_line_length: usize,
+    // also detect path:line[:col] source locations (e.g. compiler output) as links
+    pub detect_source_locations: bool,
+    // expand tabs to spaces up to the next multiple of this many columns, 0 keeps tabs as is
+    pub tab_width: usize,
 }
 
-#[derive(Debug, Serialize)]
+// lines deserialize from their serialized form with the skipped fields (content, raw and the maps)
+// left empty, see Line::restore to rebuild them from the elements
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Line {
     pub ts: i64,
+    #[serde(rename = "tf", skip_serializing_if = "Option::is_none")]
+    pub ts_fmt: Option<String>,
+    // the line had no timestamp, so ts was defaulted to the time it was parsed
+    #[serde(rename = "tsy", default, skip_serializing_if = "std::ops::Not::not")]
+    pub ts_synthetic: bool,
+    // ms since the parser's base timestamp, if one is set
+    #[serde(rename = "el", skip_serializing_if = "Option::is_none")]
+    pub elapsed: Option<i64>,
     #[serde(rename = "n")]
     pub number: usize,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub cmd: Option<Command>,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub params: HashMap<String, String>,
+    #[serde(skip)]
+    pub raw: String,
     #[serde(skip)]
     pub content: String,
+    // start index -> (end index, kind)
     #[serde(skip)]
-    pub links: HashMap<usize, usize>,
+    pub links: HashMap<usize, (usize, LinkKind)>,
     #[serde(skip)]
     pub ansis: HashMap<usize, Vec<ANSISequence>>,
     #[serde(skip)]
     pub highlights: HashMap<usize, usize>,
+    // start index -> index of the matched term, only set when highlighting multiple terms
+    #[serde(skip)]
+    pub highlight_groups: HashMap<usize, u8>,
+    // foreground the line's elements start with, until the log sets or resets colors itself
+    #[serde(skip)]
+    pub level_fg: Option<Color>,
+    // whether text outside of highlights is dimmed, for making search matches stand out. Serialized
+    // so Line::restore can tell that dimming apart from the log's own faint text
+    #[serde(rename = "dm", default, skip_serializing_if = "std::ops::Not::not")]
+    pub dim_unmatched: bool,
+    // styles left unreset by the previous line, when styles persist across lines
+    #[serde(skip)]
+    pub start_styles: Styles,
+    // left out of serialization, also as a group child, e.g. debug lines while they're hidden
+    #[serde(skip)]
+    pub hidden: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub group: Option<Group>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub truncated: bool,
+    // number of chars dropped from content when truncated
+    #[serde(skip)]
+    pub truncated_chars: usize,
     // TODO(robherley): maybe remove elements from this struct
     pub elements: Vec<Element>,
 }
 
 impl Line {
     pub fn new(number: usize, id: Option<&str>, raw: &str) -> Self {
-        let (ts, content) = Self::parse_ts(id, raw);
-        let (cmd, content) = Self::parse_cmd(content);
-        let (content, ansis) = extract_ansi(content);
-
-        let links: HashMap<usize, usize> = LinkFinder::new()
-            .kinds(&[linkify::LinkKind::Url])
-            .links(&content)
-            .map(|link| (link.start(), link.end()))
-            .collect();
+        Self::with_options(number, id, raw, &ParseOptions::default())
+    }
+
+    pub fn with_options(
+        number: usize,
+        id: Option<&str>,
+        raw: &str,
+        options: &ParseOptions,
+    ) -> Self {
+        Self::with_carry(number, id, raw, "", options)
+    }
+
+    // like with_options, with carry (the start of an escape sequence split off the end of the
+    // previous line) prepended to the content once the timestamp and command are parsed
+    pub(crate) fn with_carry(
+        number: usize,
+        id: Option<&str>,
+        raw: &str,
+        carry: &str,
+        options: &ParseOptions,
+    ) -> Self {
+        // strip the line terminator (if any) and a single stray trailing carriage return
+        let stripped = raw.strip_suffix('\n').unwrap_or(raw);
+        let stripped = stripped.strip_suffix('\r').unwrap_or(stripped);
+
+        let (ts, content) = Self::parse_ts(id, stripped);
+        // otherwise default to current time
+        let ts_synthetic = ts.is_none();
+        let ts = ts.unwrap_or_else(|| Utc::now().timestamp_millis());
+        let (cmd, params, mut content) = Self::parse_cmd(content);
+        content.insert_str(0, carry);
+        let content = apply_erase_in_line(content);
+        // escapes past the max length aren't kept, so there's no need to parse them all
+        let (mut content, mut ansis, dropped) = match options.max_line_length {
+            0 => {
+                let (content, ansis) = extract_ansi(content);
+                (content, ansis, 0)
+            }
+            max => extract_ansi_truncated(content, max),
+        };
+        if options.tab_width > 0 {
+            Self::expand_tabs(&mut content, &mut ansis, options.tab_width);
+        }
+
+        Self::from_parts(
+            number,
+            raw,
+            (ts, ts_synthetic),
+            (cmd, params),
+            (content, ansis, dropped),
+            options,
+        )
+    }
+
+    // a line whose content is kept exactly as written, without parsing commands or escapes. Only
+    // the timestamp is parsed, and links are still found. An endgroup command is the exception, so
+    // a verbatim group can still be closed
+    pub fn verbatim(number: usize, id: Option<&str>, raw: &str, options: &ParseOptions) -> Self {
+        let stripped = raw.strip_suffix('\n').unwrap_or(raw);
+        let stripped = stripped.strip_suffix('\r').unwrap_or(stripped);
+
+        let (ts, content) = Self::parse_ts(id, stripped);
+        if Self::parse_cmd(content.clone()).0 == Some(Command::EndGroup) {
+            return Self::with_options(number, id, raw, options);
+        }
+
+        let ts_synthetic = ts.is_none();
+        let ts = ts.unwrap_or_else(|| Utc::now().timestamp_millis());
+        Self::from_parts(
+            number,
+            raw,
+            (ts, ts_synthetic),
+            (None, HashMap::new()),
+            (content, HashMap::new(), 0),
+            options,
+        )
+    }
+
+    // truncates the already parsed content (dropped chars were already cut from it) and finds
+    // links in what's left, then builds the elements
+    fn from_parts(
+        number: usize,
+        raw: &str,
+        (ts, ts_synthetic): (i64, bool),
+        (cmd, params): (Option<Command>, HashMap<String, String>),
+        (mut content, mut ansis, dropped): (String, HashMap<usize, Vec<ANSISequence>>, usize),
+        options: &ParseOptions,
+    ) -> Self {
+        let truncated_chars = dropped + Self::truncate(&mut content, &mut ansis, options);
+        let links = Self::links(&content, truncated_chars > 0, options);
 
         let mut line = Self {
             number,
             cmd,
+            params,
             ts,
+            ts_fmt: None,
+            ts_synthetic,
+            elapsed: None,
+            raw: raw.to_string(),
             content,
             links,
             ansis,
             highlights: HashMap::new(),
+            highlight_groups: HashMap::new(),
+            level_fg: None,
+            dim_unmatched: false,
+            start_styles: Styles::new(),
+            hidden: false,
             elements: Vec::new(),
             group: None,
+            truncated: truncated_chars > 0,
+            truncated_chars,
         };
 
         // TODO(robherley): fix this/make it not awkward
@@ -100,117 +346,928 @@ impl Line {
         line
     }
 
-    pub fn matches(&self) -> usize {
-        let mut matches = self.highlights.len();
+    // the effective styles at the byte index, indices past the end return the final styles. Like
+    // the elements, these start from the styles carried over from the previous line and the level
+    // color, and text outside of highlights is dim when dimming unmatched text
+    pub fn styles_at(&self, index: usize) -> Styles {
+        let mut indices: Vec<&usize> = self.ansis.keys().filter(|&&i| i <= index).collect();
+        indices.sort();
 
-        if let Some(ref group) = self.group {
-            matches += group
-                .children
+        let mut styles = self.start_styles.clone();
+        if self.level_fg.is_some() {
+            styles.fg = self.level_fg.clone();
+        }
+        for i in indices {
+            styles.apply_ansis(&self.ansis[i]);
+        }
+
+        if let Some((start, _)) = self
+            .highlights
+            .iter()
+            .find(|(&start, &end)| start <= index && index < end)
+        {
+            styles.highlight = true;
+            styles.highlight_group = self.highlight_groups.get(start).copied();
+        } else if self.dim_unmatched {
+            styles.dim = true;
+        }
+
+        styles
+    }
+
+    // the (start, end) of the link at the byte index, if any
+    pub fn link_at(&self, index: usize) -> Option<(usize, usize)> {
+        self.links
+            .iter()
+            .find(|(&start, &(end, _))| start <= index && index < end)
+            .map(|(&start, &(end, _))| (start, end))
+    }
+
+    // iterates over this line and then any group children (recursively), in display order
+    pub fn iter(&self) -> Iter<'_> {
+        Iter { stack: vec![self] }
+    }
+
+    pub fn byte_len(&self) -> usize {
+        self.content.len()
+    }
+
+    pub fn char_len(&self) -> usize {
+        self.content.chars().count()
+    }
+
+    // the number of terminal columns content takes up. Each grapheme cluster (e.g. an emoji with
+    // modifiers or a char with combining marks) is one glyph, 2 columns wide for wide chars like
+    // CJK and emoji
+    pub fn display_width(&self) -> usize {
+        self.content
+            .graphemes(true)
+            .map(|grapheme| grapheme.width().min(2))
+            .sum()
+    }
+
+    // serializes just this line as compact json. A group's children are left out so that every line
+    // can be emitted as its own record
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string(&self.to_record()?)
+    }
+
+    pub(crate) fn to_record(&self) -> serde_json::Result<serde_json::Value> {
+        let mut value = serde_json::to_value(self)?;
+        if let Some(group) = value
+            .get_mut("group")
+            .and_then(|group| group.as_object_mut())
+        {
+            group.remove("children");
+        }
+        Ok(value)
+    }
+
+    // reconstructs the styled content with each parsed ansi sequence re-emitted as its own escape
+    // code (see ANSISequence::to_escape)
+    pub fn to_ansi(&self) -> String {
+        let mut indices: Vec<&usize> = self.ansis.keys().collect();
+        indices.sort();
+
+        let mut out = String::with_capacity(self.content.len());
+        let mut last = 0;
+        for &i in indices {
+            out.push_str(&self.content[last..i]);
+            self.ansis[&i]
                 .iter()
-                .map(|line| line.highlights.len())
-                .sum::<usize>();
+                .for_each(|seq| out.push_str(&seq.to_escape()));
+            last = i;
         }
+        out.push_str(&self.content[last..]);
 
-        matches
+        out
     }
 
-    pub fn highlight(&mut self, search_term: &str) {
-        if search_term.is_empty() {
-            let had_highlights = !self.highlights.is_empty();
-            self.highlights.clear();
-            if had_highlights {
-                self.elements = build_elements(self);
-            }
+    // the elements soft-wrapped into rows of at most width visible chars, see element::wrap
+    pub fn word_wrap(&self, width: usize) -> Vec<Vec<Element>> {
+        wrap(&self.elements, width)
+    }
+
+    // converts a byte index into content to a utf-16 code unit index, which is what js strings use
+    pub fn utf16_index(&self, byte_idx: usize) -> usize {
+        let end = byte_idx.min(self.content.len());
+        self.content
+            .get(..end)
+            .map_or(end, |prefix| prefix.chars().map(char::len_utf16).sum())
+    }
+
+    // links keyed by utf-16 start index, with utf-16 end indices
+    pub fn utf16_links(&self) -> HashMap<usize, (usize, LinkKind)> {
+        self.links
+            .iter()
+            .map(|(&start, &(end, kind))| (self.utf16_index(start), (self.utf16_index(end), kind)))
+            .collect()
+    }
+
+    // highlights keyed by utf-16 start index, with utf-16 end indices
+    pub fn utf16_highlights(&self) -> HashMap<usize, usize> {
+        self.highlights
+            .iter()
+            .map(|(&start, &end)| (self.utf16_index(start), self.utf16_index(end)))
+            .collect()
+    }
+
+    // replaces the content, e.g. to redact or edit a line, keeping its timestamp, number, command
+    // and group. Escapes can't be recovered from plain text, so ansis are cleared. The new content
+    // is highlighted for terms, like highlight (for one term) or highlight_terms (for several)
+    pub fn set_content(&mut self, content: &str, terms: &[String]) {
+        let lowered: Vec<String> = terms.iter().map(|term| term.to_lowercase()).collect();
+        self.set_content_with_options(
+            content,
+            &ParseOptions::default(),
+            &lowered,
+            terms.len() > 1,
+            &mut MatchBudget::default(),
+        );
+    }
+
+    // like set_content, with links (and truncation and tabs) found with options like with_options
+    // does and the highlights for the already lowercased terms spent from budget. raw is replaced
+    // so edits also apply to the raw text
+    pub(crate) fn set_content_with_options<T: AsRef<str>>(
+        &mut self,
+        content: &str,
+        options: &ParseOptions,
+        terms: &[T],
+        grouped: bool,
+        budget: &mut MatchBudget,
+    ) {
+        let mut content = content.to_string();
+        let mut ansis = HashMap::new();
+        if options.tab_width > 0 {
+            Self::expand_tabs(&mut content, &mut ansis, options.tab_width);
+        }
 
+        self.truncated_chars = Self::truncate(&mut content, &mut ansis, options);
+        self.truncated = self.truncated_chars > 0;
+        let links = Self::links(&content, self.truncated, options);
+
+        let terminator = if self.raw.ends_with('\n') { "\n" } else { "" };
+        self.raw = format!("{}{}", content, terminator);
+        self.content = content;
+        self.links = links;
+        self.ansis = ansis;
+
+        // the old content's matches are gone, so they no longer count against the budget
+        budget.refund(self.highlights.len());
+        let (highlights, groups) = Self::find_highlights(&self.content, terms, grouped, budget);
+        self.highlights = highlights;
+        self.highlight_groups = groups;
+        self.elements = build_elements(self);
+    }
+
+    // replaces tabs with spaces up to the next multiple of width visible chars, shifting the ansi
+    // indices after each tab by the bytes it grew by
+    fn expand_tabs(
+        content: &mut String,
+        ansis: &mut HashMap<usize, Vec<ANSISequence>>,
+        width: usize,
+    ) {
+        if !content.contains('\t') {
             return;
         }
 
-        self.highlights = self
-            .content
-            .to_lowercase()
-            .match_indices(search_term.to_lowercase().as_str())
-            .map(|(i, _)| (i, i + search_term.len()))
+        let mut expanded = String::with_capacity(content.len());
+        // (byte index of a tab in the original content, bytes added by it), in order
+        let mut shifts: Vec<(usize, usize)> = Vec::new();
+        // the columns added by the tabs so far
+        let mut added = 0;
+        for (col, (i, ch)) in content.char_indices().enumerate() {
+            if ch == '\t' {
+                let spaces = width - (col + added) % width;
+                expanded.extend(std::iter::repeat_n(' ', spaces));
+                shifts.push((i, spaces - 1));
+                added += spaces - 1;
+            } else {
+                expanded.push(ch);
+            }
+        }
+
+        let mut sorted: Vec<(usize, Vec<ANSISequence>)> = ansis.drain().collect();
+        sorted.sort_unstable_by_key(|(idx, _)| *idx);
+        let mut tabs = shifts.into_iter().peekable();
+        let mut shift = 0;
+        *ansis = sorted
+            .into_iter()
+            .map(|(idx, seqs)| {
+                while let Some((_, added)) = tabs.next_if(|(tab, _)| *tab < idx) {
+                    shift += added;
+                }
+                (idx + shift, seqs)
+            })
             .collect();
+        *content = expanded;
+    }
 
-        self.elements = build_elements(self);
+    // truncates content to the max line length in chars (if any), dropping any ansis outside the
+    // retained region. Returns the number of dropped chars
+    fn truncate(
+        content: &mut String,
+        ansis: &mut HashMap<usize, Vec<ANSISequence>>,
+        options: &ParseOptions,
+    ) -> usize {
+        if options.max_line_length == 0 {
+            return 0;
+        }
+        let cut = match content.char_indices().nth(options.max_line_length) {
+            Some((cut, _)) => cut,
+            None => return 0,
+        };
+
+        let dropped = content[cut..].chars().count();
+        content.truncate(cut);
+        ansis.retain(|&idx, _| idx < cut);
+
+        dropped
+    }
+
+    // the links and source locations in content. When it was truncated, a link running up to the
+    // end may have been cut in half, so it's dropped entirely
+    fn links(
+        content: &str,
+        truncated: bool,
+        options: &ParseOptions,
+    ) -> HashMap<usize, (usize, LinkKind)> {
+        let mut links = Self::find_links(content, options);
+        if options.detect_source_locations {
+            Self::find_source_locations(content, &mut links);
+        }
+        if truncated {
+            links.retain(|_, (end, _)| *end < content.len());
+        }
+        links
+    }
+
+    fn find_links(content: &str, options: &ParseOptions) -> HashMap<usize, (usize, LinkKind)> {
+        let mut finder = LinkFinder::new();
+        if options.detect_emails {
+            finder.kinds(&[linkify::LinkKind::Url, linkify::LinkKind::Email]);
+        } else {
+            finder.kinds(&[linkify::LinkKind::Url]);
+        }
+
+        finder
+            .links(content)
+            .filter(|link| {
+                if options.link_schemes.is_empty() || *link.kind() != linkify::LinkKind::Url {
+                    return true;
+                }
+
+                let scheme = link
+                    .as_str()
+                    .split_once(':')
+                    .map_or("", |(scheme, _)| scheme);
+                options
+                    .link_schemes
+                    .iter()
+                    .any(|allowed| allowed.eq_ignore_ascii_case(scheme))
+            })
+            .map(|link| {
+                let kind = match link.kind() {
+                    linkify::LinkKind::Email => LinkKind::Email,
+                    _ => LinkKind::Url,
+                };
+                (link.start(), (link.end(), kind))
+            })
+            .collect()
+    }
+
+    // adds source locations to links, skipping any that overlap an already detected link (e.g. a
+    // url with a port)
+    fn find_source_locations(content: &str, links: &mut HashMap<usize, (usize, LinkKind)>) {
+        for token in content.split_whitespace() {
+            let offset = token.as_ptr() as usize - content.as_ptr() as usize;
+
+            // surrounding punctuation, like the trailing colon in "src/main.rs:42:10: error"
+            let trimmed = token.trim_start_matches(['(', '[', '\'', '"']);
+            let start = offset + token.len() - trimmed.len();
+            let trimmed = trimmed.trim_end_matches([':', ',', ';', '.', ')', ']', '\'', '"']);
+            let end = start + trimmed.len();
+
+            if parse_source_location(trimmed).is_none() {
+                continue;
+            }
+
+            let overlaps = links
+                .iter()
+                .any(|(&link_start, &(link_end, _))| start < link_end && link_start < end);
+            if !overlaps {
+                links.insert(start, (end, LinkKind::Source));
+            }
+        }
+    }
+
+    // the displayed title of a group header: the group's title if set, the header's content
+    // otherwise. None for lines without a group
+    pub fn group_title(&self) -> Option<&str> {
+        let group = self.group.as_ref()?;
+        Some(group.title.as_deref().unwrap_or(&self.content))
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.cmd == Some(Command::Error)
+    }
 
+    pub fn is_warning(&self) -> bool {
+        self.cmd == Some(Command::Warning)
+    }
+
+    pub fn is_debug(&self) -> bool {
+        self.cmd == Some(Command::Debug)
+    }
+
+    // hides (or shows) the line and group children if they're debug lines
+    pub(crate) fn hide_debug(&mut self, hide: bool) {
+        self.hidden = hide && self.is_debug();
         if let Some(ref mut group) = self.group {
             group
                 .children
                 .iter_mut()
-                .for_each(|child| child.highlight(search_term));
+                .for_each(|child| child.hide_debug(hide));
         }
     }
 
-    fn parse_ts<'a>(id: Option<&'a str>, raw: &str) -> (i64, String) {
+    pub fn matches(&self) -> usize {
+        self.highlights.len() + self.group.as_ref().map_or(0, |group| group.matches)
+    }
+
+    // the text around each highlight, up to radius chars on either side, in order. Snippets that
+    // don't reach the start or end of content are marked with an ellipsis
+    pub fn match_snippets(&self, radius: usize) -> Vec<String> {
+        let mut highlights: Vec<(usize, usize)> = self
+            .highlights
+            .iter()
+            .map(|(&start, &end)| (start, end))
+            .collect();
+        highlights.sort();
+
+        highlights
+            .into_iter()
+            .map(|(start, end)| {
+                // highlights are found in the lowercased content, whose chars can be a different
+                // length, so the offsets may not fall on a char boundary of content
+                let start = floor_char_boundary(&self.content, start);
+                let end = floor_char_boundary(&self.content, end).max(start);
+                let before = self.content.get(..start).unwrap_or_default();
+                let begin = match radius {
+                    0 => start,
+                    _ => before
+                        .char_indices()
+                        .rev()
+                        .nth(radius - 1)
+                        .map_or(0, |(i, _)| i),
+                };
+                let after = self.content.get(end..).unwrap_or_default();
+                let finish = end
+                    + after
+                        .char_indices()
+                        .nth(radius)
+                        .map_or(after.len(), |(i, _)| i);
+
+                format!(
+                    "{}{}{}",
+                    if begin > 0 { "…" } else { "" },
+                    self.content.get(begin..finish).unwrap_or_default(),
+                    if finish < self.content.len() {
+                        "…"
+                    } else {
+                        ""
+                    }
+                )
+            })
+            .collect()
+    }
+
+    pub fn highlight(&mut self, search_term: &str) {
+        self.highlight_lowercase(
+            &search_term.to_lowercase(),
+            &mut Vec::new(),
+            &mut MatchBudget::default(),
+        );
+    }
+
+    // like highlight, but records the numbers of lines (including children) whose highlights changed
+    pub fn highlight_diff(&mut self, search_term: &str, changed: &mut Vec<usize>) {
+        self.highlight_lowercase(
+            &search_term.to_lowercase(),
+            changed,
+            &mut MatchBudget::default(),
+        );
+    }
+
+    // highlights every term, recording which term each highlight matched (see highlight_groups).
+    // Where matches of different terms overlap, the earliest starting and then longest one wins
+    pub fn highlight_terms(&mut self, terms: &[String]) {
+        let terms: Vec<String> = terms.iter().map(|term| term.to_lowercase()).collect();
+        self.highlight_terms_lowercase(&terms, &mut Vec::new(), &mut MatchBudget::default());
+    }
+
+    // search_term must already be lowercased, it's passed as is to every child so the term is
+    // only lowercased once per search instead of once per line
+    pub(crate) fn highlight_lowercase(
+        &mut self,
+        search_term: &str,
+        changed: &mut Vec<usize>,
+        budget: &mut MatchBudget,
+    ) {
+        self.apply_highlights(&[search_term], false, changed, budget);
+    }
+
+    // like highlight_lowercase, for highlight_terms
+    pub(crate) fn highlight_terms_lowercase(
+        &mut self,
+        terms: &[String],
+        changed: &mut Vec<usize>,
+        budget: &mut MatchBudget,
+    ) {
+        self.apply_highlights(terms, true, changed, budget);
+    }
+
+    fn apply_highlights<T: AsRef<str>>(
+        &mut self,
+        terms: &[T],
+        grouped: bool,
+        changed: &mut Vec<usize>,
+        budget: &mut MatchBudget,
+    ) {
+        let (highlights, groups) = Self::find_highlights(&self.content, terms, grouped, budget);
+
+        // only rebuild elements when the highlights actually changed, so lines that never
+        // matched are left untouched
+        if highlights != self.highlights || groups != self.highlight_groups {
+            self.highlights = highlights;
+            self.highlight_groups = groups;
+            self.elements = build_elements(self);
+            changed.push(self.number);
+        }
+
+        if let Some(ref mut group) = self.group {
+            group
+                .children
+                .iter_mut()
+                .for_each(|child| child.apply_highlights(terms, grouped, changed, budget));
+            group.matches = group
+                .children
+                .iter()
+                .map(|child| child.highlights.len())
+                .sum();
+        }
+    }
+
+    // the matches are spent from budget in order, the ones past it are dropped
+    fn find_highlights<T: AsRef<str>>(
+        content: &str,
+        terms: &[T],
+        grouped: bool,
+        budget: &mut MatchBudget,
+    ) -> (HashMap<usize, usize>, HashMap<usize, u8>) {
+        let mut highlights = HashMap::new();
+        let mut groups = HashMap::new();
+        if budget.exhausted() || terms.iter().all(|term| term.as_ref().is_empty()) {
+            return (highlights, groups);
+        }
+
+        let content = content.to_lowercase();
+        // (start, end, term index) of every match of every term
+        let mut matches: Vec<(usize, usize, usize)> = terms
+            .iter()
+            .enumerate()
+            .filter(|(_, term)| !term.as_ref().is_empty())
+            .flat_map(|(idx, term)| {
+                let term = term.as_ref();
+                content
+                    .match_indices(term)
+                    .map(move |(i, _)| (i, i + term.len(), idx))
+            })
+            .collect();
+        matches.sort_by(|a, b| a.0.cmp(&b.0).then(b.1.cmp(&a.1)));
+
+        let mut last_end = 0;
+        for (start, end, idx) in matches {
+            if start < last_end {
+                continue;
+            }
+            if !budget.spend() {
+                break;
+            }
+
+            highlights.insert(start, end);
+            if grouped {
+                groups.insert(start, u8::try_from(idx).unwrap_or(u8::MAX));
+            }
+            last_end = end;
+        }
+
+        (highlights, groups)
+    }
+
+    // sets cmd from the content's prefix, unless the line already has an explicit command
+    pub fn infer_level(&mut self) {
+        if self.cmd.is_none() {
+            self.cmd = Command::infer(&self.content);
+        }
+    }
+
+    // colors the line with its level's default color (see Command::level_color), unless the log
+    // already sets a foreground color at the start of the line
+    pub fn apply_level_color(&mut self) {
+        let explicit = self.ansis.get(&0).is_some_and(|seqs| {
+            seqs.iter().any(|seq| {
+                matches!(
+                    seq,
+                    ANSISequence::SetFG4(_)
+                        | ANSISequence::SetFG8(_)
+                        | ANSISequence::SetFG24(_, _, _)
+                )
+            })
+        });
+
+        let level_fg = match self.cmd {
+            Some(cmd) if !explicit => cmd.level_color(),
+            _ => None,
+        };
+
+        if level_fg != self.level_fg {
+            self.level_fg = level_fg;
+            self.elements = build_elements(self);
+        }
+    }
+
+    // the styles in effect at the end of the line, starting from start_styles. Highlights and level
+    // colors aren't set by the log, so they're left out
+    pub fn end_styles(&self) -> Styles {
+        let mut idxs: Vec<&usize> = self.ansis.keys().collect();
+        idxs.sort_unstable();
+
+        let mut styles = self.start_styles.clone();
+        for idx in idxs {
+            styles.apply_ansis(&self.ansis[idx]);
+        }
+        styles
+    }
+
+    pub(crate) fn set_start_styles(&mut self, styles: Styles) {
+        if styles != self.start_styles {
+            self.start_styles = styles;
+            self.elements = build_elements(self);
+        }
+    }
+
+    // dims (or undims) the text outside of highlights, in group children too. Lines whose elements
+    // were rebuilt are added to changed
+    pub(crate) fn set_dim_unmatched(&mut self, dim: bool, changed: &mut Vec<usize>) {
+        if dim != self.dim_unmatched {
+            self.dim_unmatched = dim;
+            self.elements = build_elements(self);
+            changed.push(self.number);
+        }
+
+        if let Some(ref mut group) = self.group {
+            group
+                .children
+                .iter_mut()
+                .for_each(|child| child.set_dim_unmatched(dim, changed));
+        }
+    }
+
+    pub fn format_ts(&mut self, format: Option<&TimestampFormat>) {
+        self.ts_fmt = format.and_then(|format| format.format(self.ts));
+
+        if let Some(ref mut group) = self.group {
+            group
+                .children
+                .iter_mut()
+                .for_each(|child| child.format_ts(format));
+        }
+    }
+
+    // sets elapsed to the ms since base (clamped at 0), or clears it if there's no base
+    pub fn set_elapsed(&mut self, base: Option<i64>) {
+        self.elapsed = base.map(|base| (self.ts - base).max(0));
+
+        if let Some(ref mut group) = self.group {
+            group
+                .children
+                .iter_mut()
+                .for_each(|child| child.set_elapsed(base));
+        }
+    }
+
+    // returns None for the timestamp if the line has neither a timestamp prefix or id
+    #[allow(clippy::single_match)]
+    fn parse_ts(id: Option<&str>, raw: &str) -> (Option<i64>, String) {
         // extract timestamp from beginning of line (completed logs)
-        if raw.len() >= 28 {
-            match &raw[..28].parse::<DateTime<Utc>>() {
-                Ok(ts) => {
-                    // 29 chars: skip the timestamp and the space
-                    return (ts.timestamp_millis(), raw[29..].to_string());
-                }
-                Err(_) => {}
+        // get() returns None instead of panicking when byte 28 is inside a multibyte char
+        match raw.get(..28).map(|prefix| prefix.parse::<DateTime<Utc>>()) {
+            Some(Ok(ts)) => {
+                // skip the timestamp and the space after it, which may be missing at the end of a line
+                // or replaced by whatever char follows the timestamp
+                let rest = &raw[28..];
+                let content = rest.strip_prefix(' ').unwrap_or(rest);
+                return (Some(ts.timestamp_millis()), content.to_string());
             }
+            _ => {}
         }
 
         // extract timestamp from id e.g. 1696290982067-0 (streaming logs)
         match id.and_then(|id| id.split_once('-')) {
             Some((unix_ms, _)) => match unix_ms.parse::<i64>() {
                 Ok(unix_ms) => {
-                    return (unix_ms, raw.to_string());
+                    return (Some(unix_ms), raw.to_string());
                 }
                 Err(_) => {}
             },
             None => {}
         }
 
-        // otherwise default to current time
-        (Utc::now().timestamp_millis(), raw.to_string())
+        (None, raw.to_string())
     }
 
-    fn parse_cmd(raw: String) -> (Option<Command>, String) {
+    fn parse_cmd(raw: String) -> (Option<Command>, HashMap<String, String>, String) {
+        if let Some(parsed) = Self::parse_workflow_cmd(&raw) {
+            return parsed;
+        }
+
         let start = match raw {
             ref r if r.starts_with("##[") => Some(3),
             ref r if r.starts_with("[") => Some(1),
             _ => None,
         };
 
-        match start {
-            Some(start) => match raw[start..].split_once(']') {
-                Some((cmd, content)) => match Command::from(cmd) {
-                    Some(cmd) => (Some(cmd), content.to_string()),
-                    None => (None, raw),
-                },
-                None => (None, raw),
-            },
-            None => (None, raw),
+        let parsed = start.and_then(|start| {
+            let (cmd, content) = raw[start..].split_once(']')?;
+            Some((Command::from(cmd)?, content))
+        });
+
+        match parsed {
+            // legacy azure style annotations can have properties, e.g. ##[error]file=foo.cs;line=3
+            Some((cmd @ (Command::Error | Command::Warning | Command::Notice), content))
+                if start == Some(3) =>
+            {
+                let (params, content) = Self::parse_legacy_params(content);
+                (Some(cmd), params, content.to_string())
+            }
+            Some((cmd, content)) => (Some(cmd), HashMap::new(), content.to_string()),
+            None => (None, HashMap::new(), raw),
         }
     }
 
+    // workflow commands as written to stdout by steps, e.g. "::error file=app.js,line=1::boom".
+    // Returns None for unknown commands, so they're kept as regular content
+    fn parse_workflow_cmd(raw: &str) -> Option<(Option<Command>, HashMap<String, String>, String)> {
+        let (head, message) = raw.strip_prefix("::")?.split_once("::")?;
+        let (name, props) = head.split_once(' ').unwrap_or((head, ""));
+        let cmd = Command::from(name)?;
+
+        let params = props
+            .split(',')
+            .filter_map(|pair| {
+                let (key, value) = pair.split_once('=')?;
+                Some((key.trim().to_string(), unescape_workflow(value, true)))
+            })
+            .filter(|(key, _)| !key.is_empty())
+            .collect();
+
+        Some((Some(cmd), params, unescape_workflow(message, false)))
+    }
+
+    // parses a leading key=value;key=value block, returning the params and the rest of the content
+    fn parse_legacy_params(content: &str) -> (HashMap<String, String>, &str) {
+        let (block, rest) = match content.split_once(' ') {
+            Some((block, rest)) => (block, rest),
+            None => (content, ""),
+        };
+
+        let params: Option<HashMap<String, String>> = block
+            .split(';')
+            .map(|pair| {
+                let (key, value) = pair.split_once('=')?;
+                if key.is_empty() || !key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+                    return None;
+                }
+                Some((key.to_string(), value.to_string()))
+            })
+            .collect();
+
+        match params {
+            Some(params) => (params, rest),
+            None => (HashMap::new(), content),
+        }
+    }
+
+    // makes the line a group header, like a ##[group] line, with an open group for children
     pub fn start_group(&mut self) {
         if self.group.is_none() {
             self.group = Some(Group::new());
+            self.cmd = Some(Command::Group);
         }
     }
 
-    pub fn end_group(&mut self) {
+    // ends the group, collapsing it if collapsed is set and it has no error children
+    pub fn end_group(&mut self, collapsed: bool) {
         if let Some(ref mut group) = self.group {
+            if group.ended {
+                return;
+            }
+
             group.ended = true;
+            group.collapsed = collapsed && !group.children.iter().any(|child| child.is_error());
         }
     }
 
+    // rebuilds the fields that aren't serialized from the elements of a deserialized line: content,
+    // links, highlights and ansis (as a reset plus the sequences for each run's styles, so level
+    // colors become explicit ones). raw is rebuilt from those without the timestamp prefix and
+    // truncated_chars is lost
+    pub fn restore(&mut self) {
+        let mut content = String::new();
+        let mut links = HashMap::new();
+        let mut ansis: HashMap<usize, Vec<ANSISequence>> = HashMap::new();
+        let mut highlights = HashMap::new();
+        let mut highlight_groups = HashMap::new();
+
+        let mut styles = Styles::new();
+        // start and term of the highlight being read
+        let mut highlight: Option<(usize, Option<u8>)> = None;
+        // text dimmed for being outside of the search matches isn't faint in the log itself
+        let dimmed = self.dim_unmatched;
+        let mut push_text = |content: &mut String, text: &str, text_styles: &Styles| {
+            let ansi_styles = Styles {
+                highlight: false,
+                highlight_group: None,
+                dim: text_styles.dim && (text_styles.highlight || !dimmed),
+                ..text_styles.clone()
+            };
+            if ansi_styles != styles {
+                let mut seqs = if styles.is_empty() {
+                    Vec::new()
+                } else {
+                    vec![ANSISequence::Reset]
+                };
+                seqs.extend(ansi_styles.to_ansis());
+                ansis.insert(content.len(), seqs);
+                styles = ansi_styles;
+            }
+
+            let group = text_styles.highlight.then_some(text_styles.highlight_group);
+            if highlight.map(|(_, current)| current) != group {
+                if let Some((start, current)) = highlight.take() {
+                    highlights.insert(start, content.len());
+                    if let Some(current) = current {
+                        highlight_groups.insert(start, current);
+                    }
+                }
+                highlight = group.map(|group| (content.len(), group));
+            }
+
+            content.push_str(text);
+        };
+
+        for element in &self.elements {
+            match element {
+                Element::Text(text, text_styles) => push_text(&mut content, text, text_styles),
+                Element::Link(_, children) | Element::SourceRef { children, .. } => {
+                    let start = content.len();
+                    for child in children {
+                        if let Element::Text(text, text_styles) = child {
+                            push_text(&mut content, text, text_styles);
+                        }
+                    }
+
+                    let kind = element.link_kind().unwrap_or(LinkKind::Url);
+                    links.insert(start, (content.len(), kind));
+                }
+            }
+        }
+
+        if let Some((start, current)) = highlight {
+            highlights.insert(start, content.len());
+            if let Some(current) = current {
+                highlight_groups.insert(start, current);
+            }
+        }
+
+        self.content = content;
+        self.links = links;
+        self.ansis = ansis;
+        self.highlights = highlights;
+        self.highlight_groups = highlight_groups;
+        self.raw = self.to_ansi();
+
+        // restored parsers start without dimming, so the elements are rebuilt without it
+        if dimmed {
+            self.dim_unmatched = false;
+            self.elements = build_elements(self);
+        }
+
+        if let Some(ref mut group) = self.group {
+            group.children.iter_mut().for_each(Line::restore);
+            group.matches = group
+                .children
+                .iter()
+                .map(|child| child.highlights.len())
+                .sum();
+        }
+    }
+
+    // adds a child to the line's group, starting one (see start_group) if the line isn't a group
+    // header yet. Groups built this way are the same as the parser's, they stay open until
+    // end_group is called
     pub fn add_child(&mut self, child: Line) {
-        match self.group {
-            Some(ref mut group) => group.children.push(child),
-            None => {
-                self.group = Some(Group {
-                    children: vec![child],
-                    ended: false,
-                });
+        self.start_group();
+        if let Some(ref mut group) = self.group {
+            group.matches += child.highlights.len();
+            group.children.push(child);
+        }
+    }
+}
+
+// caps the number of highlights recorded across several lines, matches past the cap are dropped
+#[derive(Debug, Clone, Default)]
+pub(crate) struct MatchBudget {
+    // None for no cap
+    remaining: Option<usize>,
+    // whether any match was dropped
+    pub capped: bool,
+}
+
+impl MatchBudget {
+    // a max of 0 doesn't cap matches
+    pub fn new(max: usize) -> Self {
+        Self {
+            remaining: (max > 0).then_some(max),
+            capped: false,
+        }
+    }
+
+    // spends one match, or returns false (and marks the budget capped) once none are left
+    fn spend(&mut self) -> bool {
+        match self.remaining.as_mut() {
+            None => true,
+            Some(0) => {
+                self.capped = true;
+                false
+            }
+            Some(remaining) => {
+                *remaining -= 1;
+                true
             }
         }
     }
+
+    // whether nothing is left and a match was already dropped, so later lines can be skipped
+    // without searching them
+    fn exhausted(&self) -> bool {
+        self.capped && self.remaining == Some(0)
+    }
+
+    // gives back the highlights a line no longer has, e.g. after its content was replaced
+    fn refund(&mut self, count: usize) {
+        if let Some(remaining) = self.remaining.as_mut() {
+            *remaining += count;
+        }
+    }
+}
+
+// the nearest char boundary at or before idx, clamped to the end of s
+fn floor_char_boundary(s: &str, idx: usize) -> usize {
+    let mut idx = idx.min(s.len());
+    while !s.is_char_boundary(idx) {
+        idx -= 1;
+    }
+    idx
+}
+
+// undoes the escaping the runner's toolkit applies to workflow command messages and properties
+// https://github.com/actions/toolkit/blob/main/packages/core/src/command.ts
+fn unescape_workflow(value: &str, property: bool) -> String {
+    let mut value = value.replace("%0D", "\r").replace("%0A", "\n");
+    if property {
+        value = value.replace("%3A", ":").replace("%2C", ",");
+    }
+    value.replace("%25", "%")
+}
+
+pub struct Iter<'a> {
+    stack: Vec<&'a Line>,
+}
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = &'a Line;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let line = self.stack.pop()?;
+        if let Some(ref group) = line.group {
+            self.stack.extend(group.children.iter().rev());
+        }
+
+        Some(line)
+    }
 }
 
 impl From<&str> for Line {
@@ -219,24 +1276,85 @@ impl From<&str> for Line {
     }
 }
 
-#[derive(Debug, Serialize)]
+// splits a path:line[:col] source location into its parts. The path must have a file extension, so
+// times ("12:30:45") and ports ("localhost:8080") aren't mistaken for locations
+pub(crate) fn parse_source_location(token: &str) -> Option<(&str, usize, Option<usize>)> {
+    let is_number = |value: &str| !value.is_empty() && value.chars().all(|ch| ch.is_ascii_digit());
+
+    let (rest, last) = token.rsplit_once(':')?;
+    if !is_number(last) {
+        return None;
+    }
+
+    let (path, line, col) = match rest.rsplit_once(':') {
+        Some((path, line)) if is_number(line) => (path, line, Some(last)),
+        _ => (rest, last, None),
+    };
+
+    let name = path.rsplit(['/', '\\']).next()?;
+    let (_, ext) = name.rsplit_once('.')?;
+    if ext.is_empty() || !ext.chars().all(|ch| ch.is_ascii_alphanumeric()) {
+        return None;
+    }
+
+    let col = match col {
+        Some(col) => Some(col.parse().ok()?),
+        None => None,
+    };
+
+    Some((path, line.parse().ok()?, col))
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Group {
+    #[serde(serialize_with = "serialize_shown")]
     pub children: Vec<Line>,
     pub ended: bool,
+    // groups are only collapsed once ended, still streaming groups are always expanded
+    #[serde(rename = "c")]
+    pub collapsed: bool,
+    // displayed title taken from the first child when the header itself is empty, see
+    // Parser::set_group_title_from_child. The child stays in children too
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    // raw endgroup line that closed the group, since it's not kept as a child
+    #[serde(skip)]
+    pub end_raw: Option<String>,
+    // cached number of highlights across the children, kept up to date by add_child and highlight
+    #[serde(skip)]
+    pub matches: usize,
+}
+
+// serializes the lines that aren't hidden, without copying them
+pub(crate) struct Shown<'a>(pub &'a [Line]);
+
+impl Serialize for Shown<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_seq(self.0.iter().filter(|line| !line.hidden))
+    }
+}
+
+fn serialize_shown<S>(lines: &[Line], serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    Shown(lines).serialize(serializer)
 }
 
 impl Group {
     pub fn new() -> Self {
-        Self {
-            ended: false,
-            children: Vec::new(),
-        }
+        Self::default()
     }
 }
 
 #[cfg(test)]
+#[allow(clippy::unnecessary_cast, clippy::manual_range_contains)]
 mod tests {
     use super::*;
+    use crate::style::Color;
 
     #[test]
     fn commands() {
@@ -250,6 +1368,7 @@ mod tests {
             ("warning", Some(Command::Warning)),
             ("group", Some(Command::Group)),
             ("endgroup", Some(Command::EndGroup)),
+            ("section", Some(Command::Section)),
             ("foo", None),
         ];
 
@@ -264,24 +1383,247 @@ mod tests {
         }
     }
 
+    #[test]
+    fn workflow_commands() {
+        let line = Line::new(1, None, "::error::boom");
+        assert_eq!(line.cmd, Some(Command::Error));
+        assert_eq!(line.content, "boom");
+        assert!(line.params.is_empty());
+
+        let line = Line::new(1, None, "::group::Title");
+        assert_eq!(line.cmd, Some(Command::Group));
+        assert_eq!(line.content, "Title");
+
+        let line = Line::new(1, None, "::endgroup::");
+        assert_eq!(line.cmd, Some(Command::EndGroup));
+        assert_eq!(line.content, "");
+
+        let line = Line::new(
+            1,
+            None,
+            "::warning file=app.js,line=1,title=a%2C b%3A c::50%25 done::ok",
+        );
+        assert_eq!(line.cmd, Some(Command::Warning));
+        assert_eq!(
+            line.params,
+            HashMap::from([
+                ("file".to_string(), "app.js".to_string()),
+                ("line".to_string(), "1".to_string()),
+                ("title".to_string(), "a, b: c".to_string()),
+            ])
+        );
+        assert_eq!(line.content, "50% done::ok");
+
+        for raw in [
+            "::foo::bar",
+            "::add-mask::secret",
+            "::error",
+            "a ::error::b",
+        ] {
+            let line = Line::new(1, None, raw);
+            assert_eq!(line.cmd, None, "{}", raw);
+            assert_eq!(line.content, raw);
+        }
+    }
+
+    #[test]
+    fn legacy_params() {
+        let line = Line::new(1, None, "##[error]file=foo.cs;line=3");
+        assert_eq!(line.cmd, Some(Command::Error));
+        assert_eq!(
+            line.params,
+            HashMap::from([
+                ("file".to_string(), "foo.cs".to_string()),
+                ("line".to_string(), "3".to_string()),
+            ])
+        );
+        assert_eq!(line.content, "");
+
+        let line = Line::new(1, None, "##[warning]file=a.rs;line=1;col=2 unused variable");
+        assert_eq!(line.cmd, Some(Command::Warning));
+        assert_eq!(line.params.len(), 3);
+        assert_eq!(line.content, "unused variable");
+
+        let line = Line::new(1, None, "##[warning]x = y is deprecated");
+        assert!(line.params.is_empty());
+        assert_eq!(line.content, "x = y is deprecated");
+
+        let line = Line::new(1, None, "##[debug]TOKEN=abc");
+        assert!(line.params.is_empty());
+        assert_eq!(line.content, "TOKEN=abc");
+
+        let line = Line::new(1, None, "##[section]file=a.cs Starting");
+        assert_eq!(line.cmd, Some(Command::Section));
+        assert!(line.params.is_empty());
+        assert_eq!(line.content, "file=a.cs Starting");
+    }
+
+    #[test]
+    fn command_round_trip() {
+        let names = [
+            "command", "debug", "error", "info", "notice", "verbose", "warning", "group",
+            "endgroup", "section",
+        ];
+
+        for (i, name) in names.iter().enumerate() {
+            let cmd = Command::from(name).unwrap();
+            let value = cmd as u8;
+            assert_eq!(value as usize, i + 1);
+            assert_eq!(Command::from_u8(value), Some(cmd));
+            assert_eq!(serde_json::to_string(&cmd).unwrap(), value.to_string());
+        }
+
+        assert_eq!(Command::from_u8(0), None);
+        assert_eq!(Command::from_u8(11), None);
+        assert_eq!(serde_json::to_string(&Command::Section).unwrap(), "10");
+    }
+
+    #[test]
+    fn infer_level() {
+        let cases = [
+            ("ERROR: boom", Some(Command::Error)),
+            ("warning: careful", Some(Command::Warning)),
+            ("[INFO] hello", Some(Command::Info)),
+            ("[Warning] mixed case", Some(Command::Warning)),
+            ("[GROUP] not a level", None),
+            ("Debug: details", Some(Command::Debug)),
+            ("##[notice]error: explicit wins", Some(Command::Notice)),
+            ("no level here", None),
+            ("errors: not a prefix", None),
+        ];
+
+        for (raw, expected) in cases {
+            let mut line = Line::from(raw);
+            line.infer_level();
+            assert_eq!(line.cmd, expected, "{}", raw);
+        }
+    }
+
+    #[test]
+    fn level_color() {
+        let mut line = Line::from("##[error]boom \u{1b}[0mplain");
+        line.apply_level_color();
+        assert_eq!(
+            line.elements,
+            vec![
+                Element::Text(
+                    "boom ".to_string(),
+                    Styles {
+                        fg: Some(Color::Bit4(1)),
+                        ..Styles::new()
+                    }
+                ),
+                Element::Text("plain".to_string(), Styles::new()),
+            ]
+        );
+
+        // colors from the log win
+        let mut line = Line::from("##[error]\u{1b}[32mgreen");
+        line.apply_level_color();
+        assert_eq!(line.level_fg, None);
+        assert_eq!(
+            line.elements,
+            vec![Element::Text(
+                "green".to_string(),
+                Styles {
+                    fg: Some(Color::Bit4(2)),
+                    ..Styles::new()
+                }
+            )]
+        );
+
+        let mut line = Line::from("##[warning]careful");
+        line.highlight("care");
+        line.apply_level_color();
+        assert_eq!(
+            line.elements[0],
+            Element::Text(
+                "care".to_string(),
+                Styles {
+                    highlight: true,
+                    fg: Some(Color::Bit4(3)),
+                    ..Styles::new()
+                }
+            )
+        );
+
+        let mut line = Line::from("no level");
+        line.apply_level_color();
+        assert_eq!(
+            line.elements[0],
+            Element::Text("no level".to_string(), Styles::new())
+        );
+    }
+
+    #[test]
+    fn line_endings() {
+        for raw in ["foo", "foo\n", "foo\r\n", "foo\r"] {
+            let line = Line::new(1, None, raw);
+            assert_eq!(line.content, "foo");
+            assert_eq!(line.raw, raw);
+        }
+
+        let line = Line::new(1, None, "foo\r\r");
+        assert_eq!(line.content, "foo\r");
+    }
+
+    #[test]
+    fn progress_frames() {
+        let raw = concat!(
+            "2024-01-15T00:14:49.2830954Z ",
+            "\u{1b}[1mabc123\u{1b}[0m: Downloading  12.5MB/48.1MB\r\u{1b}[K",
+            "\u{1b}[1mabc123\u{1b}[0m: Downloading  40.2MB/48.1MB\r\u{1b}[K",
+            "\u{1b}[1mabc123\u{1b}[0m: Pull complete\r\n",
+        );
+        let line = Line::new(1, None, raw);
+
+        assert_eq!(line.content, "abc123: Pull complete");
+        assert_eq!(line.raw, raw);
+        assert_eq!(
+            line.elements[0],
+            Element::Text(
+                "abc123".to_string(),
+                Styles {
+                    bold: true,
+                    ..Styles::new()
+                }
+            )
+        );
+    }
+
+    #[test]
+    fn spinner_frames() {
+        let raw = "2024-01-15T00:14:49.2830954Z ⠋ building\r⠙ building\r⠹ building done\n";
+        let line = Line::new(1, None, raw);
+
+        assert_eq!(line.content, "⠹ building done");
+        assert_eq!(line.raw, raw);
+    }
+
     #[test]
     fn timestamps() {
         let line = Line::new(1, None, "2024-01-15T00:14:43.5805748Z foo");
         assert_eq!(line.ts, 1705277683580);
+        assert!(!line.ts_synthetic);
 
         let line = Line::new(1, Some("1705277683580-0"), "foo");
         assert_eq!(line.ts, 1705277683580);
+        assert!(!line.ts_synthetic);
 
         let line = Line::new(1, Some("foo"), "bar");
         let diff = (Utc::now().timestamp_millis() - line.ts).abs();
-        assert!(diff >= 0 && diff < 1000)
+        assert!(diff >= 0 && diff < 1000);
+        assert!(line.ts_synthetic);
+        assert!(serde_json::to_string(&line)
+            .unwrap()
+            .contains(r#""tsy":true"#));
     }
 
     #[test]
     fn ansi() {
         let line = Line::new(1, None, "\u{1b}[31mfoo\u{1b}[0m");
         assert_eq!(line.ansis.len(), 2);
-        assert_eq!(line.ansis[&(0 as usize)], vec![ANSISequence::SetFG8(1)]);
+        assert_eq!(line.ansis[&(0 as usize)], vec![ANSISequence::SetFG4(1)]);
         assert_eq!(line.ansis[&(3 as usize)], vec![ANSISequence::Reset]);
     }
 
@@ -289,7 +1631,101 @@ mod tests {
     fn links() {
         let line = Line::new(1, None, "foo https://reb.gg bar");
         assert_eq!(line.links.len(), 1);
-        assert_eq!(line.links[&(4 as usize)], 18);
+        assert_eq!(line.links[&4], (18, LinkKind::Url));
+
+        let text = "foo https://reb.gg/user@example.com bar@example.com";
+        let line = Line::new(1, None, text);
+        assert_eq!(line.links.len(), 1);
+
+        let options = ParseOptions {
+            detect_emails: true,
+            ..ParseOptions::default()
+        };
+        let line = Line::with_options(1, None, text, &options);
+        assert_eq!(line.links.len(), 2);
+        assert_eq!(line.links[&4], (35, LinkKind::Url));
+        assert_eq!(line.links[&36], (51, LinkKind::Email));
+    }
+
+    #[test]
+    fn link_schemes() {
+        let text = "ftp://files.reb.gg/a.tgz vscode://file/foo https://reb.gg ftp://";
+
+        let line = Line::new(1, None, text);
+        assert_eq!(line.links.len(), 3);
+
+        let options = ParseOptions {
+            link_schemes: vec!["FTP".to_string(), "https".to_string()],
+            ..ParseOptions::default()
+        };
+        let line = Line::with_options(1, None, text, &options);
+        assert_eq!(line.links.len(), 2);
+        assert_eq!(line.links[&0], (24, LinkKind::Url));
+        assert_eq!(line.links[&43], (57, LinkKind::Url));
+    }
+
+    #[test]
+    fn truncate() {
+        let options = ParseOptions {
+            max_line_length: 10,
+            ..ParseOptions::default()
+        };
+
+        let line = Line::with_options(1, None, "\u{1b}[1mshort\u{1b}[0m", &options);
+        assert!(!line.truncated);
+        assert_eq!(line.content, "short");
+        assert_eq!(line.ansis.len(), 2);
+
+        let line = Line::with_options(1, None, "0123456789\u{1b}[31mabcdef", &options);
+        assert!(line.truncated);
+        assert_eq!(line.truncated_chars, 6);
+        assert_eq!(line.content, "0123456789");
+        assert!(line.ansis.is_empty());
+
+        let line = Line::with_options(1, None, "ünïcödé ✓ truncated", &options);
+        assert!(line.truncated);
+        assert_eq!(line.char_len(), 10);
+        assert_eq!(line.byte_len(), 16);
+
+        let line = Line::with_options(1, None, "ok https://reb.gg", &options);
+        assert!(line.truncated);
+        assert_eq!(line.content, "ok https:/");
+        assert!(line.links.is_empty());
+
+        // links are found after truncating, one running up to the cut may continue past it
+        let line = Line::with_options(1, None, "https://reb.gg/foo", &options);
+        assert_eq!(line.content, "https://re");
+        assert!(line.links.is_empty());
+    }
+
+    #[test]
+    fn styles_at() {
+        let mut line = Line::new(
+            1,
+            None,
+            "foo \u{1b}[31mred\u{1b}[1m bold\u{1b}[0m https://reb.gg",
+        );
+        line.highlight("red");
+
+        assert_eq!(line.styles_at(0), Styles::new());
+
+        let red = line.styles_at(5);
+        assert_eq!(red.fg, Some(Color::Bit4(1)));
+        assert!(!red.bold);
+        assert!(red.highlight);
+
+        let bold = line.styles_at(8);
+        assert_eq!(bold.fg, Some(Color::Bit4(1)));
+        assert!(bold.bold);
+        assert!(!bold.highlight);
+
+        assert_eq!(line.styles_at(12), Styles::new());
+        assert_eq!(line.styles_at(1000), Styles::new());
+
+        assert_eq!(line.link_at(12), None);
+        assert_eq!(line.link_at(13), Some((13, 27)));
+        assert_eq!(line.link_at(26), Some((13, 27)));
+        assert_eq!(line.link_at(27), None);
     }
 
     #[test]
@@ -312,6 +1748,370 @@ mod tests {
         assert_eq!(line.highlights.len(), 0);
     }
 
+    #[test]
+    fn match_budget() {
+        let mut line = Line::new(1, None, "a a a");
+        line.add_child(Line::new(2, None, "a a"));
+        line.add_child(Line::new(3, None, "a"));
+
+        let mut budget = MatchBudget::new(4);
+        line.highlight_lowercase("a", &mut Vec::new(), &mut budget);
+        assert!(budget.capped);
+        assert_eq!(line.highlights.len(), 3);
+
+        let children = &line.group.as_ref().unwrap().children;
+        assert_eq!(children[0].highlights, HashMap::from([(0, 1)]));
+        assert!(children[1].highlights.is_empty());
+        assert_eq!(line.matches(), 4);
+
+        let mut budget = MatchBudget::new(6);
+        line.highlight_lowercase("a", &mut Vec::new(), &mut budget);
+        assert!(!budget.capped);
+        assert_eq!(line.matches(), 6);
+    }
+
+    #[test]
+    fn verbatim() {
+        let raw =
+            "2024-01-15T00:14:43.0000000Z ##[error]\u{1b}[31mnot parsed\u{1b}[0m https://reb.gg\n";
+        let line = Line::verbatim(1, None, raw, &ParseOptions::default());
+        assert_eq!(line.ts, 1705277683000);
+        assert_eq!(line.cmd, None);
+        assert_eq!(
+            line.content,
+            "##[error]\u{1b}[31mnot parsed\u{1b}[0m https://reb.gg"
+        );
+        assert!(line.ansis.is_empty());
+        assert_eq!(line.links, HashMap::from([(29, (43, LinkKind::Url))]));
+
+        let line = Line::verbatim(1, None, "##[endgroup]", &ParseOptions::default());
+        assert_eq!(line.cmd, Some(Command::EndGroup));
+    }
+
+    #[test]
+    fn display_width() {
+        let cases = [
+            ("plain", 5),
+            // a family emoji, joined with zero width joiners
+            (
+                "\u{1f468}\u{200d}\u{1f469}\u{200d}\u{1f467}\u{200d}\u{1f466}",
+                2,
+            ),
+            ("👍🏽 ok", 5),
+            // e followed by a combining acute accent
+            ("cafe\u{301}", 4),
+            ("日本語", 6),
+            ("\u{1b}[31m日本\u{1b}[0m ok", 7),
+        ];
+
+        for (raw, expected) in cases {
+            assert_eq!(Line::from(raw).display_width(), expected, "{:?}", raw);
+        }
+    }
+
+    #[test]
+    fn set_content() {
+        let mut line = Line::new(
+            3,
+            None,
+            "2024-01-15T00:14:43.0000000Z ##[error]\u{1b}[31mtoken abc123\u{1b}[0m, see https://reb.gg\n",
+        );
+        line.highlight("TOKEN");
+        assert_eq!(line.highlights.len(), 1);
+
+        line.set_content(
+            "token *** redacted, see https://github.com and its token",
+            &["TOKEN".to_string()],
+        );
+        assert_eq!(line.number, 3);
+        assert_eq!(line.ts, 1705277683000);
+        assert_eq!(line.cmd, Some(Command::Error));
+        assert!(line.ansis.is_empty());
+        assert_eq!(line.links, HashMap::from([(24, (42, LinkKind::Url))]));
+        assert_eq!(line.highlights, HashMap::from([(0, 5), (51, 56)]));
+        assert_eq!(
+            line.raw,
+            "token *** redacted, see https://github.com and its token\n"
+        );
+        assert_eq!(
+            line.elements,
+            vec![
+                Element::Text(
+                    "token".to_string(),
+                    Styles {
+                        highlight: true,
+                        ..Styles::new()
+                    }
+                ),
+                Element::Text(" *** redacted, see ".to_string(), Styles::new()),
+                Element::Link(
+                    "https://github.com".to_string(),
+                    vec![Element::Text(
+                        "https://github.com".to_string(),
+                        Styles::new()
+                    )]
+                ),
+                Element::Text(" and its ".to_string(), Styles::new()),
+                Element::Text(
+                    "token".to_string(),
+                    Styles {
+                        highlight: true,
+                        ..Styles::new()
+                    }
+                ),
+            ]
+        );
+
+        // several terms record their term indices
+        let mut line = Line::from("foo bar");
+        line.set_content("bar foo bar", &["bar".to_string(), "foo".to_string()]);
+        assert_eq!(
+            line.highlight_groups,
+            HashMap::from([(0, 0), (4, 1), (8, 0)])
+        );
+    }
+
+    #[test]
+    fn highlight_lowercase() {
+        let mut line = Line::new(1, None, "foo BAR baz");
+        line.add_child(Line::new(2, None, "bar"));
+        line.highlight("Bar");
+
+        let mut lowered = Line::new(1, None, "foo BAR baz");
+        lowered.add_child(Line::new(2, None, "bar"));
+        let mut changed = Vec::new();
+        lowered.highlight_lowercase("bar", &mut changed, &mut MatchBudget::default());
+
+        assert_eq!(changed, vec![1, 2]);
+        assert_eq!(lowered.highlights, line.highlights);
+        assert_eq!(lowered.elements, line.elements);
+        let child = &lowered.group.as_ref().unwrap().children[0];
+        assert_eq!(child.highlights, HashMap::from([(0, 3)]));
+    }
+
+    #[test]
+    fn source_locations() {
+        let options = ParseOptions {
+            detect_source_locations: true,
+            ..ParseOptions::default()
+        };
+
+        let line = Line::with_options(
+            1,
+            None,
+            "error[E0308]: mismatched types at src/main.rs:42:10: expected u8",
+            &options,
+        );
+        assert_eq!(line.links, HashMap::from([(34, (51, LinkKind::Source))]));
+        assert_eq!(&line.content[34..51], "src/main.rs:42:10");
+
+        // urls with ports are links, bare ports and times aren't locations
+        let line = Line::with_options(
+            1,
+            None,
+            "listening on http://localhost:8080/health localhost:8080 at 12:30:45",
+            &options,
+        );
+        assert_eq!(line.links, HashMap::from([(13, (41, LinkKind::Url))]));
+
+        // disabled by default
+        let line = Line::new(1, None, "src/main.rs:42:10");
+        assert!(line.links.is_empty());
+    }
+
+    #[test]
+    fn parse_source_locations() {
+        let cases = [
+            ("src/main.rs:42:10", Some(("src/main.rs", 42, Some(10)))),
+            ("main.c:3", Some(("main.c", 3, None))),
+            ("C:\\src\\lib.rs:1:2", Some(("C:\\src\\lib.rs", 1, Some(2)))),
+            ("src/main.rs", None),
+            ("localhost:8080", None),
+            ("12:30:45", None),
+            ("src/main.rs:", None),
+            ("src/main.rs:+4", None),
+        ];
+
+        for (token, expected) in cases {
+            assert_eq!(parse_source_location(token), expected, "{}", token);
+        }
+    }
+
+    #[test]
+    fn utf16_offsets() {
+        let mut line = Line::new(1, None, "café 😀 https://reb.gg");
+        line.highlight("reb");
+
+        // é is 2 bytes but 1 utf-16 unit, 😀 is 4 bytes but 2 utf-16 units
+        assert_eq!(line.links, HashMap::from([(11, (25, LinkKind::Url))]));
+        assert_eq!(
+            line.utf16_links(),
+            HashMap::from([(8, (22, LinkKind::Url))])
+        );
+        assert_eq!(line.highlights, HashMap::from([(19, 22)]));
+        assert_eq!(line.utf16_highlights(), HashMap::from([(16, 19)]));
+
+        let utf16: Vec<u16> = line.content.encode_utf16().collect();
+        assert_eq!(String::from_utf16(&utf16[8..22]).unwrap(), "https://reb.gg");
+    }
+
+    #[test]
+    fn highlight_terms() {
+        let mut line = Line::new(1, None, "error: a warning about an Error");
+        line.highlight_terms(&["error".to_string(), "warning".to_string()]);
+
+        assert_eq!(line.highlights, HashMap::from([(0, 5), (9, 16), (26, 31)]));
+        assert_eq!(
+            line.highlight_groups,
+            HashMap::from([(0, 0), (9, 1), (26, 0)])
+        );
+        assert_eq!(line.styles_at(10).highlight_group, Some(1));
+        assert_eq!(
+            line.elements[2],
+            Element::Text(
+                "warning".to_string(),
+                Styles {
+                    highlight: true,
+                    highlight_group: Some(1),
+                    ..Styles::new()
+                }
+            )
+        );
+
+        // overlapping matches prefer the earliest, then the longest
+        line.highlight_terms(&[
+            "warn".to_string(),
+            "a warning".to_string(),
+            "ing".to_string(),
+        ]);
+        assert_eq!(line.highlights, HashMap::from([(7, 16)]));
+        assert_eq!(line.highlight_groups, HashMap::from([(7, 1)]));
+
+        // a single term search has no groups
+        line.highlight("error");
+        assert_eq!(line.highlights.len(), 2);
+        assert!(line.highlight_groups.is_empty());
+        assert_eq!(line.styles_at(0).highlight_group, None);
+    }
+
+    #[test]
+    fn to_ansi() {
+        let line = Line::new(
+            1,
+            None,
+            "\u{1b}[1mbold\u{1b}[22m \u{1b}[31;44m4-bit\u{1b}[38;5;200m8-bit \u{1b}[38;2;1;2;3m24-bit\u{1b}[0m",
+        );
+
+        let ansi = line.to_ansi();
+        assert_eq!(
+            ansi,
+            "\u{1b}[1mbold\u{1b}[22m \u{1b}[31m\u{1b}[44m4-bit\u{1b}[38;5;200m8-bit \u{1b}[38;2;1;2;3m24-bit\u{1b}[0m"
+        );
+
+        let reparsed = Line::new(1, None, &ansi);
+        assert_eq!(reparsed.content, line.content);
+        assert_eq!(reparsed.ansis, line.ansis);
+        assert_eq!(reparsed.elements, line.elements);
+    }
+
+    #[test]
+    fn multibyte_timestamp_boundary() {
+        // byte 28 falls inside the 3 byte "…", which used to panic when slicing
+        let raw = "2024-01-15T00:14:49.283095…Z foo";
+        assert!(!raw.is_char_boundary(28));
+        let line = Line::new(1, None, raw);
+        assert!(line.ts_synthetic);
+        assert_eq!(line.content, raw);
+
+        let line = Line::new(1, None, "ééééééééééééééééééééé");
+        assert_eq!(line.content, "ééééééééééééééééééééé");
+
+        // a bare timestamp without anything after it
+        let line = Line::new(1, None, "2024-01-15T00:14:49.2830954Z");
+        assert!(!line.ts_synthetic);
+        assert_eq!(line.content, "");
+
+        // a multibyte char right after the timestamp instead of the space is kept
+        let line = Line::new(1, None, "2024-01-15T00:14:49.2830954Zéfoo");
+        assert!(!line.ts_synthetic);
+        assert_eq!(line.content, "éfoo");
+    }
+
+    #[test]
+    fn expand_tabs() {
+        let options = ParseOptions {
+            tab_width: 4,
+            ..ParseOptions::default()
+        };
+
+        let line = Line::with_options(1, None, "\tfoo", &options);
+        assert_eq!(line.content, "    foo");
+
+        // aligned to the next multiple of the width
+        let line = Line::with_options(1, None, "ab\tc\td\t\te", &options);
+        assert_eq!(line.content, "ab  c   d       e");
+
+        // ansi indices after a tab are shifted, escapes don't count as columns
+        let mut line =
+            Line::with_options(1, None, "\u{1b}[1mab\u{1b}[0m\tcd https://reb.gg", &options);
+        assert_eq!(line.content, "ab  cd https://reb.gg");
+        assert_eq!(
+            line.ansis,
+            HashMap::from([
+                (0, vec![ANSISequence::Bold]),
+                (2, vec![ANSISequence::Reset])
+            ])
+        );
+        assert_eq!(line.links, HashMap::from([(7, (21, LinkKind::Url))]));
+
+        line = Line::with_options(1, None, "x\ty\u{1b}[1mz", &options);
+        assert_eq!(line.ansis, HashMap::from([(5, vec![ANSISequence::Bold])]));
+
+        // a tab within a highlight
+        line = Line::with_options(1, None, "ab\tcd", &options);
+        line.highlight("b  c");
+        assert_eq!(line.highlights, HashMap::from([(1, 5)]));
+        assert_eq!(
+            line.elements[1],
+            Element::Text(
+                "b  c".to_string(),
+                Styles {
+                    highlight: true,
+                    ..Styles::new()
+                }
+            )
+        );
+
+        // disabled by default
+        let line = Line::new(1, None, "a\tb");
+        assert_eq!(line.content, "a\tb");
+    }
+
+    #[test]
+    #[cfg(any(feature = "wasm", feature = "native"))]
+    fn to_json() {
+        let mut line = Line::new(1, None, "##[group]title");
+        line.add_child(Line::new(2, None, "child"));
+
+        let value: serde_json::Value = serde_json::from_str(&line.to_json().unwrap()).unwrap();
+        assert_eq!(value["n"], 1);
+        assert_eq!(value["group"]["ended"], false);
+        assert!(value["group"].get("children").is_none());
+    }
+
+    #[test]
+    fn iter() {
+        let mut line = Line::new(1, None, "one");
+        let mut nested = Line::new(3, None, "three");
+        nested.add_child(Line::new(4, None, "four"));
+        line.add_child(Line::new(2, None, "two"));
+        line.add_child(nested);
+        line.add_child(Line::new(5, None, "five"));
+
+        let numbers: Vec<usize> = line.iter().map(|line| line.number).collect();
+        assert_eq!(numbers, vec![1, 2, 3, 4, 5]);
+    }
+
     #[test]
     fn matches() {
         let mut line = Line::new(1, None, "foo bar baz bAr");
@@ -323,4 +2123,63 @@ mod tests {
         line.highlight("world");
         assert_eq!(line.matches(), 2);
     }
+
+    #[test]
+    fn match_snippets() {
+        let mut line = Line::new(
+            1,
+            None,
+            "the quick brown fox jumps over the lazy dog, then the fox naps",
+        );
+        line.highlight("fox");
+        assert_eq!(
+            line.match_snippets(6),
+            vec!["…brown fox jumps…", "…n the fox naps"]
+        );
+
+        // multi-byte chars count as one
+        line = Line::new(1, None, "ééé match ééé");
+        line.highlight("match");
+        assert_eq!(line.match_snippets(2), vec!["…é match é…"]);
+        assert_eq!(line.match_snippets(0), vec!["…match…"]);
+        assert_eq!(line.match_snippets(20), vec!["ééé match ééé"]);
+
+        line.highlight("nothing");
+        assert!(line.match_snippets(2).is_empty());
+
+        // lowercasing changes the length of these chars, so the offsets don't line up with
+        // content's char boundaries
+        line = Line::new(1, None, "ẞab");
+        line.highlight("a");
+        assert_eq!(line.match_snippets(2), vec!["ẞab"]);
+        // and past its end, since these grow
+        line = Line::new(1, None, "İİ x İ");
+        line.highlight("i̇");
+        assert_eq!(line.match_snippets(1).len(), 3);
+    }
+
+    #[test]
+    fn cached_group_matches() {
+        let recount =
+            |line: &Line| -> usize { line.iter().map(|line| line.highlights.len()).sum() };
+
+        let mut line = Line::new(1, None, "world");
+        line.start_group();
+        line.highlight("world");
+        assert_eq!(line.matches(), 1);
+
+        let mut child = Line::new(2, None, "world world");
+        child.highlight("world");
+        line.add_child(child);
+        line.add_child(Line::new(3, None, "hello world"));
+        assert_eq!(line.matches(), 3);
+        assert_eq!(line.matches(), recount(&line));
+
+        line.highlight("o");
+        assert_eq!(line.matches(), 5);
+        assert_eq!(line.matches(), recount(&line));
+
+        line.highlight("");
+        assert_eq!(line.matches(), 0);
+    }
 }
@@ -0,0 +1,233 @@
+use crate::element::{plain_text, Element};
+use crate::line::{Command, Line};
+
+// renders lines as github flavored markdown, groups become <details> blocks and colors are dropped
+pub fn to_markdown(lines: &[Line]) -> String {
+    let mut out = String::new();
+    write_lines(&mut out, lines);
+    out
+}
+
+fn write_lines(out: &mut String, lines: &[Line]) {
+    for line in lines.iter().filter(|line| !line.hidden) {
+        write_line(out, line);
+    }
+}
+
+fn write_line(out: &mut String, line: &Line) {
+    if let Some(group) = &line.group {
+        // markdown isn't rendered inside <summary>, so only the plain text is kept
+        out.push_str(if group.collapsed {
+            "<details>"
+        } else {
+            "<details open>"
+        });
+        out.push_str("<summary>");
+        match &group.title {
+            Some(title) => out.push_str(&escape_html(title)),
+            None => out.push_str(&escape_html(&plain_text(&line.elements))),
+        }
+        out.push_str("</summary>\n\n");
+        write_lines(out, &group.children);
+        out.push_str("\n</details>\n\n");
+        return;
+    }
+
+    match line.cmd {
+        Some(Command::Error) => out.push_str("❌ "),
+        Some(Command::Warning) => out.push_str("⚠️ "),
+        _ => {}
+    }
+
+    let start = out.len();
+    let mut after_marker = false;
+    for element in &line.elements {
+        write_element(out, element, &mut after_marker);
+    }
+    escape_block_start(out, start);
+
+    // two trailing spaces force a line break instead of joining lines into a paragraph
+    out.push_str("  \n");
+}
+
+// after_marker is whether out ends with the closing marker of an emphasized run, which the next
+// run's opening marker would run into
+fn write_element(out: &mut String, element: &Element, after_marker: &mut bool) {
+    match element {
+        Element::Text(content, styles) => {
+            let text = if styles.conceal {
+                element.plain_text()
+            } else {
+                escape_markdown(content)
+            };
+
+            let marker = match (styles.bold, styles.italic) {
+                (true, true) => "***",
+                (true, false) => "**",
+                (false, true) => "*",
+                (false, false) => "",
+            };
+
+            // emphasis can't start or end with whitespace, so keep it outside of the markers
+            let trimmed = text.trim();
+            if marker.is_empty() || trimmed.is_empty() {
+                out.push_str(&text);
+                *after_marker &= text.is_empty();
+                return;
+            }
+
+            let start = text.len() - text.trim_start().len();
+            let end = start + trimmed.len();
+            out.push_str(&text[..start]);
+            // markers touching the previous run's, like **foo*****bar***, don't parse as two runs,
+            // so that run is written with html tags instead
+            if start == 0 && *after_marker {
+                let (open, close) = match (styles.bold, styles.italic) {
+                    (true, true) => ("<b><i>", "</i></b>"),
+                    (true, false) => ("<b>", "</b>"),
+                    _ => ("<i>", "</i>"),
+                };
+                out.push_str(open);
+                out.push_str(trimmed);
+                out.push_str(close);
+                *after_marker = false;
+            } else {
+                out.push_str(marker);
+                out.push_str(trimmed);
+                out.push_str(marker);
+                *after_marker = end == text.len();
+            }
+            out.push_str(&text[end..]);
+        }
+        Element::Link(href, children) => {
+            out.push('[');
+            let mut in_link = false;
+            for child in children {
+                write_element(out, child, &mut in_link);
+            }
+            out.push_str("](");
+            out.push_str(&href.replace(' ', "%20").replace(')', "%29"));
+            out.push(')');
+            *after_marker = false;
+        }
+        Element::SourceRef { children, .. } => {
+            for child in children {
+                write_element(out, child, after_marker);
+            }
+        }
+    }
+}
+
+fn escape_markdown(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for ch in text.chars() {
+        if matches!(
+            ch,
+            '\\' | '*' | '_' | '`' | '[' | ']' | '<' | '>' | '~' | '|'
+        ) {
+            escaped.push('\\');
+        }
+        escaped.push(ch);
+    }
+    escaped
+}
+
+// escapes what would start a heading, list item or blockquote at the beginning of the line
+// written to out from start on, e.g. "# not a heading". '>' is already escaped everywhere
+fn escape_block_start(out: &mut String, start: usize) {
+    let line = &out[start..];
+    let indent = line.len() - line.trim_start_matches(' ').len();
+    let rest = &line[indent..];
+
+    let digits = rest.len()
+        - rest
+            .trim_start_matches(|ch: char| ch.is_ascii_digit())
+            .len();
+    let at = match rest[digits..].chars().next() {
+        Some('#' | '-' | '+') if digits == 0 => indent,
+        // ordered list items, e.g. "1. item" or "1) item", are escaped at their delimiter
+        Some('.' | ')') if digits > 0 => indent + digits,
+        _ => return,
+    };
+    out.insert(start + at, '\\');
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+
+    #[test]
+    fn group() {
+        let mut parser = Parser::new();
+        parser.set_raw(
+            "##[group]Run <tests>\n##[error]failed, see https://github.com\nok\n##[endgroup]\n##[warning]careful\n",
+        );
+
+        assert_eq!(
+            parser.to_markdown(),
+            "<details open><summary>Run &lt;tests&gt;</summary>\n\n\
+             ❌ failed, see [https://github.com](https://github.com)  \n\
+             ok  \n\
+             \n</details>\n\n\
+             ⚠️ careful  \n"
+        );
+    }
+
+    #[test]
+    fn emphasis() {
+        let line = Line::from("\u{1b}[1mbold \u{1b}[3mboth\u{1b}[22m italic\u{1b}[0m *plain*");
+
+        let mut out = String::new();
+        write_line(&mut out, &line);
+        assert_eq!(out, "**bold** ***both*** *italic* \\*plain\\*  \n");
+    }
+
+    #[test]
+    fn adjacent_emphasis() {
+        let line =
+            Line::from("\u{1b}[1mfoo\u{1b}[3mbar\u{1b}[0m \u{1b}[3mbaz\u{1b}[1mqux\u{1b}[0m");
+
+        let mut out = String::new();
+        write_line(&mut out, &line);
+        assert_eq!(out, "**foo**<b><i>bar</i></b> *baz*<b><i>qux</i></b>  \n");
+    }
+
+    #[test]
+    fn block_start() {
+        let cases = [
+            ("# not a heading", "\\# not a heading  \n"),
+            ("- item", "\\- item  \n"),
+            ("  + item", "  \\+ item  \n"),
+            ("12. item", "12\\. item  \n"),
+            ("1) item", "1\\) item  \n"),
+            ("> quote", "\\> quote  \n"),
+            ("2024 and # - +", "2024 and # - +  \n"),
+        ];
+
+        for (raw, expected) in cases {
+            let mut out = String::new();
+            write_line(&mut out, &Line::from(raw));
+            assert_eq!(out, expected, "{:?}", raw);
+        }
+    }
+
+    #[test]
+    fn highlighted_link() {
+        let mut line = Line::from("go to https://example.com now");
+        line.highlight("example");
+
+        let mut out = String::new();
+        write_line(&mut out, &line);
+        assert_eq!(
+            out,
+            "go to [https://example.com](https://example.com) now  \n"
+        );
+    }
+}
@@ -1,37 +1,172 @@
-use crate::line::{Command, Line};
+use crate::ansi::split_dangling_escape;
+use crate::error::Error;
+#[cfg(any(feature = "wasm", feature = "native"))]
+use crate::line::Shown;
+use crate::line::{Command, Line, MatchBudget, ParseOptions};
+use crate::markdown::to_markdown;
+use crate::style::Styles;
+use crate::timestamp::TimestampFormat;
 use serde::Serialize;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
+#[cfg(feature = "wasm")]
 use wasm_bindgen::prelude::*;
 
-#[wasm_bindgen]
-#[derive(Debug, Serialize)]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Debug, Clone, Serialize)]
 pub struct Parser {
     idx: usize,
     lines: Vec<Line>,
     search: String,
+    // lowercased terms of a multi-term search, empty unless set with setSearchTerms
+    #[serde(skip)]
+    search_terms: Vec<String>,
+    #[serde(skip)]
+    ts_format: Option<TimestampFormat>,
+    #[serde(skip)]
+    base_ts: Option<i64>,
+    #[serde(skip)]
+    auto_base_ts: bool,
+    #[serde(skip)]
+    infer_levels: bool,
+    #[serde(skip)]
+    level_colors: bool,
+    #[serde(skip)]
+    verbatim_groups: bool,
+    #[serde(skip)]
+    show_debug: bool,
+    #[serde(skip)]
+    dim_non_matches: bool,
+    #[serde(skip)]
+    persist_styles: bool,
+    // styles the last added line left unreset, the next line starts with them when persisting
+    #[serde(skip)]
+    carry_styles: Styles,
+    #[serde(skip)]
+    group_title_from_child: bool,
+    #[serde(skip)]
+    collapse_groups: bool,
+    #[serde(skip)]
+    tolerant_groups: bool,
+    // whether a group was started since the lines were last cleared, for tolerant_groups
+    #[serde(skip)]
+    seen_group: bool,
+    #[serde(skip)]
+    collapse_blank_lines: bool,
+    #[serde(skip)]
+    include_raw_maps: bool,
+    #[serde(skip)]
+    options: ParseOptions,
+    // 0 for no cap
+    #[serde(skip)]
+    max_matches: usize,
+    // what's left of max_matches for the current search
+    #[serde(skip)]
+    match_budget: MatchBudget,
+    // unterminated escape sequence at the end of the last added line, prepended to the next one
+    #[serde(skip)]
+    carry: String,
+    // unterminated last line of the last appendRaw chunk, added once a later chunk completes it
+    #[serde(skip)]
+    partial: String,
 }
 
-#[wasm_bindgen]
+// counts for a set of added lines, displayed lines include group children but not the endgroup lines
+// that close groups
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct Summary {
+    pub lines: usize,
+    pub groups: usize,
+    pub errors: usize,
+    pub warnings: usize,
+}
+
+impl Summary {
+    fn count(&mut self, cmd: Option<Command>) {
+        self.lines += 1;
+        match cmd {
+            Some(Command::Group) => self.groups += 1,
+            Some(Command::Error) => self.errors += 1,
+            Some(Command::Warning) => self.warnings += 1,
+            _ => {}
+        }
+    }
+}
+
+impl Default for Parser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
 impl Parser {
-    #[wasm_bindgen(constructor)]
+    #[cfg_attr(feature = "wasm", wasm_bindgen(constructor))]
     pub fn new() -> Self {
         Self {
             idx: 1,
             lines: Vec::new(),
             search: "".to_string(),
+            search_terms: Vec::new(),
+            ts_format: None,
+            base_ts: None,
+            auto_base_ts: false,
+            infer_levels: false,
+            level_colors: false,
+            verbatim_groups: false,
+            show_debug: true,
+            dim_non_matches: false,
+            persist_styles: false,
+            carry_styles: Styles::new(),
+            group_title_from_child: false,
+            collapse_groups: true,
+            tolerant_groups: false,
+            seen_group: false,
+            collapse_blank_lines: false,
+            include_raw_maps: false,
+            options: ParseOptions::default(),
+            max_matches: 0,
+            match_budget: MatchBudget::default(),
+            carry: String::new(),
+            partial: String::new(),
         }
     }
 
-    fn reset(&mut self) {
+    // clears the parsed lines, but keeps the search and options
+    fn clear(&mut self) {
         self.lines.clear();
         self.idx = 1;
+        self.carry.clear();
+        self.partial.clear();
+        self.seen_group = false;
+        self.carry_styles = Styles::new();
+        self.match_budget = MatchBudget::new(self.max_matches);
+        if self.auto_base_ts {
+            self.base_ts = None;
+        }
     }
 
     fn end_group(&mut self) {
         if let Some(line) = self.lines.last_mut() {
-            line.end_group();
+            line.end_group(self.collapse_groups);
         }
     }
 
+    // the most recently added line, which is the last child of a still open group
+    fn last_line(&self) -> Option<&Line> {
+        let last = self.lines.last()?;
+        match &last.group {
+            Some(group) if !group.ended => group.children.last().or(Some(last)),
+            _ => Some(last),
+        }
+    }
+
+    // whether the next line is added to a still open group titled "raw"
+    fn in_verbatim_group(&self) -> bool {
+        self.in_group() && self.lines.last().is_some_and(|line| line.content == "raw")
+    }
+
     fn in_group(&self) -> bool {
         if let Some(line) = self.lines.last() {
             if let Some(group) = &line.group {
@@ -42,53 +177,598 @@ impl Parser {
         false
     }
 
-    #[wasm_bindgen(js_name = stringify)]
-    pub fn stringify(&self, pretty: bool) -> Result<String, JsError> {
-        let serialize_fn = if pretty {
-            serde_json::to_string_pretty
-        } else {
-            serde_json::to_string
+    // clears all lines and the search, e.g. when switching between jobs. Options are kept
+    #[cfg_attr(feature = "wasm", wasm_bindgen(js_name = reset))]
+    pub fn reset(&mut self) {
+        self.clear();
+        self.search.clear();
+        self.search_terms.clear();
+    }
+
+    // replaces all lines, the current search (if any) is kept and applied to the new lines. Use
+    // reset to also clear the search. Returns the counts of the new lines
+    #[cfg_attr(feature = "wasm", wasm_bindgen(js_name = setRaw))]
+    pub fn set_raw(&mut self, raw: &str) -> Summary {
+        self.clear();
+        let mut summary = Summary::default();
+        // keep the line terminators so the raw text can be reconstructed exactly
+        raw.split_inclusive('\n')
+            .for_each(|line| self.push_line(None, line, "", line, &mut summary));
+        summary
+    }
+
+    // like setRaw, but appends to the lines already parsed, e.g. when polling a growing log. Chunks
+    // that re-send the end of what was already added are deduplicated: the longest run of leading
+    // lines matching the last added lines is skipped. Lines are compared by their raw text, which
+    // includes the timestamp, so repeated output at different times isn't mistaken for overlap. An
+    // unterminated last line is held back until a later chunk completes it, see flush
+    #[cfg_attr(feature = "wasm", wasm_bindgen(js_name = appendRaw))]
+    pub fn append_raw(&mut self, raw: &str) -> Summary {
+        let mut incoming: Vec<&str> = raw.split_inclusive('\n').collect();
+        let unterminated = match incoming.last() {
+            Some(line) if !line.ends_with('\n') => incoming.pop(),
+            _ => None,
         };
+        let overlap = self.overlap(&incoming);
+
+        let mut lines: Vec<Cow<str>> = incoming[overlap..]
+            .iter()
+            .chain(&unterminated)
+            .map(|line| Cow::Borrowed(*line))
+            .collect();
 
-        match serialize_fn(&self.lines) {
-            Ok(json) => Ok(json),
-            Err(err) => Err(JsError::new(&format!("{:?}", err))),
+        // the held back line is either sent again in full, or the chunk continues it
+        let partial = std::mem::take(&mut self.partial);
+        match lines.first_mut() {
+            Some(first) if !first.starts_with(partial.as_str()) => {
+                *first = Cow::Owned(partial + first);
+            }
+            None => lines.push(Cow::Owned(partial)),
+            _ => {}
+        }
+
+        if lines.last().is_some_and(|line| !line.ends_with('\n')) {
+            self.partial = lines.pop().unwrap_or_default().into_owned();
         }
+
+        let mut summary = Summary::default();
+        lines
+            .iter()
+            .for_each(|line| self.push_chunk(None, line, &mut summary));
+        summary
     }
 
-    #[wasm_bindgen(js_name = setRaw)]
-    pub fn set_raw(&mut self, raw: &str) {
-        self.reset();
-        raw.lines().for_each(|line| self.add_line("", line));
+    // like setRaw, but invalid utf-8 is replaced with U+FFFD instead of failing
+    #[cfg_attr(feature = "wasm", wasm_bindgen(js_name = setRawBytes))]
+    pub fn set_raw_bytes(&mut self, raw: &[u8]) -> Summary {
+        self.set_raw(&String::from_utf8_lossy(raw))
+    }
+
+    #[cfg_attr(feature = "wasm", wasm_bindgen(js_name = rawText))]
+    pub fn raw_text(&self) -> String {
+        let mut raw = String::new();
+        // lines from set_raw keep their terminators, streamed lines from add_line do not
+        let mut terminated = true;
+        let mut push = |line: &str| {
+            if !terminated {
+                raw.push('\n');
+            }
+            raw.push_str(line);
+            terminated = line.ends_with('\n');
+        };
+
+        // a line flushed from a carried escape has content but no raw text of its own, it's already
+        // in the raw text of the line it was split from
+        let flushed = |line: &Line| line.raw.is_empty() && !line.content.is_empty();
+        for line in self.lines.iter() {
+            if !flushed(line) {
+                push(&line.raw);
+            }
+            if let Some(ref group) = line.group {
+                group
+                    .children
+                    .iter()
+                    .filter(|child| !flushed(child))
+                    .for_each(|child| push(&child.raw));
+                if let Some(ref end_raw) = group.end_raw {
+                    push(end_raw);
+                }
+            }
+        }
+
+        raw
     }
 
-    #[wasm_bindgen(js_name = setSearch)]
+    #[cfg_attr(feature = "wasm", wasm_bindgen(js_name = setSearch))]
     pub fn set_search(&mut self, search: &str) {
+        self.set_search_diff(search);
+    }
+
+    // like setSearch, but returns the numbers of the lines whose highlights changed
+    #[cfg_attr(feature = "wasm", wasm_bindgen(js_name = setSearchDiff))]
+    pub fn set_search_diff(&mut self, search: &str) -> Vec<usize> {
         self.search = search.to_lowercase();
+        self.search_terms.clear();
+
+        self.match_budget = MatchBudget::new(self.max_matches);
+        let mut changed = Vec::new();
+        for line in self.lines.iter_mut() {
+            line.highlight_lowercase(&self.search, &mut changed, &mut self.match_budget);
+        }
+        self.update_dimming(&mut changed);
+
+        changed.sort_unstable();
+        changed.dedup();
+        changed
+    }
+
+    // searches for several terms at once, each highlight records the index of the term it matched
+    // (as "hg" in its styles) so they can be colored differently. Replaces any setSearch term
+    #[cfg_attr(feature = "wasm", wasm_bindgen(js_name = setSearchTerms))]
+    pub fn set_search_terms(&mut self, terms: Vec<String>) {
+        self.search.clear();
+        self.search_terms = terms.iter().map(|term| term.to_lowercase()).collect();
+
+        self.match_budget = MatchBudget::new(self.max_matches);
         for line in self.lines.iter_mut() {
-            line.highlight(&self.search);
+            line.highlight_terms_lowercase(
+                &self.search_terms,
+                &mut Vec::new(),
+                &mut self.match_budget,
+            );
+        }
+        self.update_dimming(&mut Vec::new());
+    }
+
+    // caps the number of highlights a search records across all lines (0, the default, doesn't
+    // cap them). Matches are counted in line order, the current search is re-applied with the cap
+    #[cfg_attr(feature = "wasm", wasm_bindgen(js_name = setMaxMatches))]
+    pub fn set_max_matches(&mut self, max: usize) {
+        self.max_matches = max;
+        if !self.search_terms.is_empty() {
+            self.set_search_terms(self.search_terms.clone());
+        } else {
+            self.set_search_diff(&self.search.clone());
+        }
+    }
+
+    // whether the current search had more matches than the max, so only the first were highlighted
+    #[cfg_attr(feature = "wasm", wasm_bindgen(js_name = matchesCapped))]
+    pub fn matches_capped(&self) -> bool {
+        self.match_budget.capped
+    }
+
+    #[cfg_attr(feature = "wasm", wasm_bindgen(js_name = clearSearch))]
+    pub fn clear_search(&mut self) {
+        if self.search.is_empty() && self.search_terms.is_empty() {
+            return;
+        }
+
+        self.set_search("");
+    }
+
+    // serializes each line's ms since base_ms as "el" (clamped at 0), e.g. for elapsed job times
+    #[cfg_attr(feature = "wasm", wasm_bindgen(js_name = setBaseTimestamp))]
+    pub fn set_base_timestamp(&mut self, base_ms: i64) {
+        self.auto_base_ts = false;
+        self.update_base_timestamp(Some(base_ms));
+    }
+
+    // when enabled, the base timestamp is the first line's timestamp
+    #[cfg_attr(feature = "wasm", wasm_bindgen(js_name = setAutoBaseTimestamp))]
+    pub fn set_auto_base_timestamp(&mut self, auto: bool) {
+        self.auto_base_ts = auto;
+        if auto {
+            self.update_base_timestamp(self.lines.first().map(|line| line.ts));
+        }
+    }
+
+    #[cfg_attr(feature = "wasm", wasm_bindgen(js_name = clearBaseTimestamp))]
+    pub fn clear_base_timestamp(&mut self) {
+        self.auto_base_ts = false;
+        self.update_base_timestamp(None);
+    }
+
+    // when enabled, lines added afterwards without an explicit command get one inferred from
+    // bare prefixes like "ERROR:" or "[warn]"
+    #[cfg_attr(feature = "wasm", wasm_bindgen(js_name = setInferLevels))]
+    pub fn set_infer_levels(&mut self, infer: bool) {
+        self.infer_levels = infer;
+    }
+
+    // when enabled, error, warning, notice and debug lines added afterwards default to a foreground
+    // color for their level (see Command::level_color). Colors set by the log itself are kept
+    #[cfg_attr(feature = "wasm", wasm_bindgen(js_name = setLevelColors))]
+    pub fn set_level_colors(&mut self, enabled: bool) {
+        self.level_colors = enabled;
+    }
+
+    // when enabled, lines added afterwards to a group titled "raw" are kept verbatim, e.g. for echoed
+    // logs that contain their own commands. Only an endgroup command is parsed in them, see
+    // Line::verbatim
+    #[cfg_attr(feature = "wasm", wasm_bindgen(js_name = setVerbatimGroups))]
+    pub fn set_verbatim_groups(&mut self, enabled: bool) {
+        self.verbatim_groups = enabled;
+    }
+
+    // whether groups are collapsed by default once they end, groups with errors are always expanded
+    #[cfg_attr(feature = "wasm", wasm_bindgen(js_name = setDefaultGroupCollapsed))]
+    pub fn set_default_group_collapsed(&mut self, collapsed: bool) {
+        self.collapse_groups = collapsed;
+    }
+
+    // when enabled, styles that a line doesn't reset carry over to the lines added after it, like
+    // a terminal would render them, instead of every line starting unstyled. This is across group
+    // boundaries too, since groups are only a rendering of the same output
+    #[cfg_attr(feature = "wasm", wasm_bindgen(js_name = setPersistStyles))]
+    pub fn set_persist_styles(&mut self, persist: bool) {
+        self.persist_styles = persist;
+        self.carry_styles = Styles::new();
+    }
+
+    // when enabled, a group header without any content takes its first child's content as the
+    // group's title, for tools that print the group name on the line after ##[group]
+    #[cfg_attr(feature = "wasm", wasm_bindgen(js_name = setGroupTitleFromChild))]
+    pub fn set_group_title_from_child(&mut self, enabled: bool) {
+        self.group_title_from_child = enabled;
+    }
+
+    // when enabled, text that doesn't match the current search is dimmed (the "d" style) so matches
+    // stand out. Nothing is dimmed while there's no search
+    #[cfg_attr(feature = "wasm", wasm_bindgen(js_name = setDimNonMatches))]
+    pub fn set_dim_non_matches(&mut self, dim: bool) {
+        self.dim_non_matches = dim;
+        self.update_dimming(&mut Vec::new());
+    }
+
+    // collapses or expands the group with the header line number, e.g. to keep a viewer's toggles.
+    // Returns false if the line isn't a group header, or the group is still streaming since those
+    // are always expanded
+    #[cfg_attr(feature = "wasm", wasm_bindgen(js_name = setGroupCollapsed))]
+    pub fn set_group_collapsed(&mut self, line_number: usize, collapsed: bool) -> bool {
+        let group = match self.find_line(line_number) {
+            Some((idx, None)) => self.lines[idx].group.as_mut(),
+            _ => None,
+        };
+
+        match group {
+            Some(group) if group.ended => {
+                group.collapsed = collapsed;
+                true
+            }
+            _ => false,
         }
     }
 
-    #[wasm_bindgen(js_name = getMatches)]
+    // when enabled, email addresses in lines added afterwards are linked with a mailto: href
+    #[cfg_attr(feature = "wasm", wasm_bindgen(js_name = setDetectEmails))]
+    pub fn set_detect_emails(&mut self, detect: bool) {
+        self.options.detect_emails = detect;
+    }
+
+    // when enabled, path:line[:col] source locations in lines added afterwards become source refs
+    #[cfg_attr(feature = "wasm", wasm_bindgen(js_name = setDetectSourceLocations))]
+    pub fn set_detect_source_locations(&mut self, detect: bool) {
+        self.options.detect_source_locations = detect;
+    }
+
+    // restricts url detection in lines added afterwards to the given schemes, empty allows any scheme
+    #[cfg_attr(feature = "wasm", wasm_bindgen(js_name = setLinkSchemes))]
+    pub fn set_link_schemes(&mut self, schemes: Vec<String>) {
+        self.options.link_schemes = schemes;
+    }
+
+    // expands tabs in lines added afterwards to spaces, aligned to multiples of width columns. 0 (the
+    // default) keeps tabs as is
+    #[cfg_attr(feature = "wasm", wasm_bindgen(js_name = setTabWidth))]
+    pub fn set_tab_width(&mut self, width: usize) {
+        self.options.tab_width = width;
+    }
+
+    // truncates lines added afterwards to at most max visible chars, 0 disables truncation
+    #[cfg_attr(feature = "wasm", wasm_bindgen(js_name = setMaxLineLength))]
+    pub fn set_max_line_length(&mut self, max: usize) {
+        self.options.max_line_length = max;
+    }
+
+    // when enabled, endgroups received before any group has started are dropped instead of being
+    // shown as regular lines (and are not kept in rawText)
+    #[cfg_attr(feature = "wasm", wasm_bindgen(js_name = setTolerantGroups))]
+    pub fn set_tolerant_groups(&mut self, tolerant: bool) {
+        self.tolerant_groups = tolerant;
+    }
+
+    // when enabled, runs of blank lines added afterwards collapse to a single blank line, the extra
+    // ones are dropped (and are not kept in rawText). Blank lines in a group only collapse with
+    // other blank lines in that group
+    #[cfg_attr(feature = "wasm", wasm_bindgen(js_name = setCollapseBlankLines))]
+    pub fn set_collapse_blank_lines(&mut self, collapse: bool) {
+        self.collapse_blank_lines = collapse;
+    }
+
+    // when enabled, serialized lines also include their links, ansis and highlights maps (keyed by
+    // byte index) for consumers that render from those instead of elements
+    #[cfg_attr(feature = "wasm", wasm_bindgen(js_name = setIncludeRawMaps))]
+    pub fn set_include_raw_maps(&mut self, include: bool) {
+        self.include_raw_maps = include;
+    }
+
+    #[cfg_attr(feature = "wasm", wasm_bindgen(js_name = getMatches))]
     pub fn matches(&self) -> usize {
-        self.lines.iter().map(|line| line.matches()).sum()
+        // the cached group counts include debug lines
+        if self.show_debug {
+            return self.lines.iter().map(|line| line.matches()).sum();
+        }
+        self.iter_display().map(|line| line.highlights.len()).sum()
+    }
+
+    // when false, debug lines (including group children) are left out of serialization, search
+    // results and match counts. They're still parsed and kept, so showing them again is cheap
+    #[cfg_attr(feature = "wasm", wasm_bindgen(js_name = setShowDebug))]
+    pub fn set_show_debug(&mut self, show: bool) {
+        self.show_debug = show;
+        for line in self.lines.iter_mut() {
+            line.hide_debug(!show);
+        }
+    }
+
+    // the header line number of the first group whose title matches name, ignoring case and
+    // surrounding whitespace
+    #[cfg_attr(feature = "wasm", wasm_bindgen(js_name = findGroup))]
+    pub fn find_group(&self, name: &str) -> Option<usize> {
+        let name = name.trim().to_lowercase();
+        self.lines
+            .iter()
+            .find(|line| {
+                line.group_title()
+                    .is_some_and(|title| title.trim().to_lowercase() == name)
+            })
+            .map(|line| line.number)
+    }
+
+    // numbers of the lines (including group children) with the command, by its serialized number
+    // (see Command), in display order. E.g. for jumping from error to error
+    #[cfg_attr(feature = "wasm", wasm_bindgen(js_name = lineNumbersForCommand))]
+    pub fn line_numbers_for_command(&self, cmd: u8) -> Vec<usize> {
+        let Some(cmd) = Command::from_u8(cmd) else {
+            return Vec::new();
+        };
+
+        self.iter_display()
+            .filter(|line| line.cmd == Some(cmd))
+            .map(|line| line.number)
+            .collect()
+    }
+
+    // the number of the first line (including group children) at or after the unix ms timestamp.
+    // This is a binary search, so it assumes timestamps are sorted, which isn't the case for
+    // synthesized timestamps
+    #[cfg_attr(feature = "wasm", wasm_bindgen(js_name = lineAtTs))]
+    pub fn line_at_ts(&self, ts: i64) -> Option<usize> {
+        let idx = self.lines.partition_point(|line| line.ts < ts);
+
+        // the previous group's children may be at or after ts, even though its header is before it
+        if let Some(group) = idx
+            .checked_sub(1)
+            .and_then(|prev| self.lines[prev].group.as_ref())
+        {
+            let child = group.children.partition_point(|child| child.ts < ts);
+            if let Some(child) = group.children.get(child) {
+                return Some(child.number);
+            }
+        }
+
+        self.lines.get(idx).map(|line| line.number)
+    }
+
+    // replaces a line's content (see Line::set_content), returns false if there's no line with that
+    // number (including group children)
+    #[cfg_attr(feature = "wasm", wasm_bindgen(js_name = updateLine))]
+    pub fn update_line(&mut self, number: usize, content: &str) -> bool {
+        let Some((idx, child)) = self.find_line(number) else {
+            return false;
+        };
+
+        // the new content is highlighted for the current search, like added lines are
+        let (terms, grouped) = if self.search_terms.is_empty() {
+            (std::slice::from_ref(&self.search), false)
+        } else {
+            (self.search_terms.as_slice(), true)
+        };
+
+        let line = &mut self.lines[idx];
+        let budget = &mut self.match_budget;
+        match (child, line.group.as_mut()) {
+            (Some(child), Some(group)) => {
+                group.children[child].set_content_with_options(
+                    content,
+                    &self.options,
+                    terms,
+                    grouped,
+                    budget,
+                );
+                group.matches = group.children.iter().map(|c| c.highlights.len()).sum();
+            }
+            _ => line.set_content_with_options(content, &self.options, terms, grouped, budget),
+        }
+        true
+    }
+
+    // removes a line, a group header is removed along with its children. Line numbers aren't
+    // reused, so the other lines keep theirs. Returns false if there's no line with that number
+    #[cfg_attr(feature = "wasm", wasm_bindgen(js_name = removeLine))]
+    pub fn remove_line(&mut self, number: usize) -> bool {
+        let Some((idx, child)) = self.find_line(number) else {
+            return false;
+        };
+
+        match (child, self.lines[idx].group.as_mut()) {
+            (Some(child), Some(group)) => {
+                let removed = group.children.remove(child);
+                group.matches -= removed.highlights.len();
+            }
+            _ => {
+                self.lines.remove(idx);
+            }
+        }
+        true
+    }
+
+    // preallocates room for at least additional more top-level lines, e.g. when the size of a log
+    // is known up front
+    #[cfg_attr(feature = "wasm", wasm_bindgen(js_name = reserveLines))]
+    pub fn reserve_lines(&mut self, additional: usize) {
+        self.lines.reserve(additional);
+    }
+
+    // keeps only the last n top-level lines. A group counts as a single line and is kept whole (with
+    // all of its children) when its header is within the window, so groups are never split
+    #[cfg_attr(feature = "wasm", wasm_bindgen(js_name = truncateTail))]
+    pub fn truncate_tail(&mut self, n: usize) {
+        let start = self.lines.len().saturating_sub(n);
+        self.lines.drain(..start);
+    }
+
+    // number of error lines, including group children and inferred levels
+    #[cfg_attr(feature = "wasm", wasm_bindgen(js_name = errorCount))]
+    pub fn error_count(&self) -> usize {
+        self.iter_display().filter(|line| line.is_error()).count()
+    }
+
+    // number of warning lines, including group children and inferred levels
+    #[cfg_attr(feature = "wasm", wasm_bindgen(js_name = warningCount))]
+    pub fn warning_count(&self) -> usize {
+        self.iter_display().filter(|line| line.is_warning()).count()
+    }
+
+    // github flavored markdown for pasting into issues, groups become <details> blocks
+    #[cfg_attr(feature = "wasm", wasm_bindgen(js_name = toMarkdown))]
+    pub fn to_markdown(&self) -> String {
+        to_markdown(&self.lines)
     }
 
-    #[wasm_bindgen(js_name = addLine)]
+    #[cfg_attr(feature = "wasm", wasm_bindgen(js_name = addLine))]
     pub fn add_line(&mut self, id: &str, raw: &str) {
         let id = if id.is_empty() { None } else { Some(id) };
-        let mut line = Line::new(self.idx, id, raw);
+        self.push_chunk(id, raw, &mut Summary::default());
+    }
+
+    // adds the line appendRaw is holding back, and any buffered text from an unterminated escape
+    // sequence as its own line
+    #[cfg_attr(feature = "wasm", wasm_bindgen(js_name = flush))]
+    pub fn flush(&mut self) {
+        let partial = std::mem::take(&mut self.partial);
+        if !partial.is_empty() {
+            self.push_chunk(None, &partial, &mut Summary::default());
+        }
+
+        if self.carry.is_empty() {
+            return;
+        }
+
+        // the carried text is already in the raw text of the line it was split from
+        let carry = std::mem::take(&mut self.carry);
+        self.push_line(None, &carry, "", "", &mut Summary::default());
+    }
+}
+
+impl Parser {
+    // pushes a streamed line, escape sequences split across chunks are carried over to the next one.
+    // Verbatim lines keep their escapes as written, so nothing is split off them
+    fn push_chunk(&mut self, id: Option<&str>, raw: &str, summary: &mut Summary) {
+        let (complete, dangling) = if self.verbatim_groups && self.in_verbatim_group() {
+            (raw, "")
+        } else {
+            split_dangling_escape(raw)
+        };
+        let carry = std::mem::replace(&mut self.carry, dangling.to_string());
+
+        self.push_line(id, complete, &carry, raw, summary);
+    }
+
+    // parses text as the next line, with carry spliced in after its timestamp and command. raw is
+    // kept as the original input for rawText
+    fn push_line(
+        &mut self,
+        id: Option<&str>,
+        text: &str,
+        carry: &str,
+        raw: &str,
+        summary: &mut Summary,
+    ) {
+        let mut line = if self.verbatim_groups && self.in_verbatim_group() {
+            Line::verbatim(self.idx, id, text, &self.options)
+        } else {
+            Line::with_carry(self.idx, id, text, carry, &self.options)
+        };
+        if text != raw {
+            line.raw = raw.to_string();
+        }
+
+        // every line passes its styles on, including endgroups and other lines that aren't kept,
+        // since a terminal would have applied their escapes all the same
+        if self.persist_styles {
+            line.set_start_styles(std::mem::take(&mut self.carry_styles));
+            self.carry_styles = line.end_styles();
+        }
+
+        if self.infer_levels {
+            line.infer_level();
+        }
+
+        if self.level_colors {
+            line.apply_level_color();
+        }
+
+        if !self.search_terms.is_empty() {
+            line.highlight_terms_lowercase(
+                &self.search_terms,
+                &mut Vec::new(),
+                &mut self.match_budget,
+            );
+        } else if !self.search.is_empty() {
+            line.highlight_lowercase(&self.search, &mut Vec::new(), &mut self.match_budget);
+        }
+
+        if self.dimming() {
+            line.set_dim_unmatched(true, &mut Vec::new());
+        }
+
+        if !self.show_debug {
+            line.hide_debug(true);
+        }
+
+        if self.ts_format.is_some() {
+            line.format_ts(self.ts_format.as_ref());
+        }
 
-        if !self.search.is_empty() {
-            line.highlight(&self.search);
+        if self.auto_base_ts && self.base_ts.is_none() {
+            self.base_ts = Some(line.ts);
         }
 
+        if self.base_ts.is_some() {
+            line.set_elapsed(self.base_ts);
+        }
+
+        if self.collapse_blank_lines && is_blank(&line) && self.last_line().is_some_and(is_blank) {
+            return;
+        }
+
+        let cmd = line.cmd;
         match line.cmd {
             Some(Command::EndGroup) => {
                 if self.in_group() {
                     self.end_group();
-                    // don't add endgroup lines when they properly close a group
+                    // don't add endgroup lines when they properly close a group, only keep the raw line
+                    if let Some(group) = self.lines.last_mut().and_then(|l| l.group.as_mut()) {
+                        group.end_raw = Some(line.raw);
+                    }
+                    return;
+                }
+
+                // when joining mid-stream, endgroups before any group was seen close a group we never
+                // received, so they're just noise
+                if self.tolerant_groups && !self.seen_group {
                     return;
                 }
 
@@ -97,12 +777,25 @@ impl Parser {
             }
             Some(Command::Group) => {
                 self.end_group();
+                self.seen_group = true;
                 line.start_group();
                 self.lines.push(line);
             }
+            // sections are headers between groups, so they close an open group instead of joining it
+            Some(Command::Section) => {
+                self.end_group();
+                self.lines.push(line);
+            }
             _ => {
                 if self.in_group() {
                     if let Some(last_line) = self.lines.last_mut() {
+                        let untitled = last_line.content.trim().is_empty();
+                        if let Some(group) = last_line.group.as_mut() {
+                            if self.group_title_from_child && untitled && group.children.is_empty()
+                            {
+                                group.title = Some(line.content.clone());
+                            }
+                        }
                         last_line.add_child(line);
                     }
                 } else {
@@ -111,80 +804,832 @@ impl Parser {
             }
         }
 
+        summary.count(cmd);
         self.idx += 1;
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::collections::HashMap;
-    use std::vec;
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+impl Parser {
+    #[wasm_bindgen(js_name = stringify)]
+    pub fn stringify_js(&self, pretty: bool) -> Result<String, JsError> {
+        self.lines_json(pretty)
+            .map_err(|err| JsError::from(Error::from(err)))
+    }
 
-    #[test]
-    fn normal() {
-        let lines = concat!(
-        "2024-01-15T00:14:43.5805748Z Requested labels: ubuntu-latest\n",
-        "2024-01-15T00:14:43.5806028Z Job defined at: robherley/tmp/.github/workflows/blank.yml@refs/heads/main\n",
-        "2024-01-15T00:14:43.5806125Z Waiting for a runner to pick up this job...\n",
-        "2024-01-15T00:14:44.2854453Z Job is waiting for a hosted runner to come online.\n",
-        "2024-01-15T00:14:46.5843551Z Job is about to start running on the hosted runner: GitHub Actions 2 (hosted)\n",
-        "2024-01-15T00:14:49.2802822Z Current runner version: '2.311.0'\n",
-        );
+    #[wasm_bindgen(js_name = toPlaintextWithTimestamps)]
+    pub fn to_plaintext_with_timestamps_js(&self, fmt: &str) -> Result<String, JsError> {
+        self.to_plaintext_with_timestamps(fmt)
+            .map_err(JsError::from)
+    }
 
-        let mut parser = Parser::new();
-        parser.set_raw(lines);
+    // an empty format disables formatted timestamps, utc=false formats in the local timezone
+    #[wasm_bindgen(js_name = setTimestampFormat)]
+    pub fn set_timestamp_format_js(&mut self, fmt: &str, utc: bool) -> Result<(), JsError> {
+        self.update_timestamp_format(fmt, utc)
+            .map_err(JsError::from)
+    }
 
-        assert_eq!(parser.lines.len(), 6);
+    // loads lines serialized with stringify, see Parser::from_lines for what is restored
+    #[wasm_bindgen(js_name = fromJson)]
+    pub fn from_json_js(json: &str) -> Result<Parser, JsError> {
+        serde_json::from_str(json)
+            .map(Parser::from_lines)
+            .map_err(|err| JsError::from(Error::from(err)))
+    }
 
-        for (i, line) in parser.lines.iter().enumerate() {
-            assert_eq!(line.number, i + 1);
-            assert_eq!(line.cmd, None);
-        }
+    #[wasm_bindgen(js_name = stringifyTree)]
+    pub fn stringify_tree_js(&self, pretty: bool) -> Result<String, JsError> {
+        self.lines_tree_json(pretty)
+            .map_err(|err| JsError::from(Error::from(err)))
     }
 
-    #[test]
-    fn with_groups() {
-        let lines = concat!(
-            "2024-01-15T00:14:49.2830954Z ##[group]Operating System\n",
-            "2024-01-15T00:14:49.2831846Z Ubuntu\n",
-            "2024-01-15T00:14:49.2832204Z 22.04.3\n",
-            "2024-01-15T00:14:49.2832638Z LTS\n",
-            "2024-01-15T00:14:49.2833085Z ##[endgroup]\n",
-            "2024-01-15T00:14:49.2833509Z ##[group]Runner Image\n",
-            "2024-01-15T00:14:49.2834023Z Image: ubuntu-22.04\n",
-            "2024-01-15T00:14:49.2834552Z Version: 20240107.1.0\n",
-            "2024-01-15T00:14:49.2835705Z Included Software: https://github.com/actions/runner-images/blob/ubuntu22/20240107.1/images/ubuntu/Ubuntu2204-Readme.md\n",
-            "2024-01-15T00:14:49.2837409Z Image Release: https://github.com/actions/runner-images/releases/tag/ubuntu22%2F20240107.1\n",
-            "2024-01-15T00:14:49.2838476Z ##[endgroup]\n",
-            "2024-01-15T00:14:49.2838965Z ##[group]Runner Image Provisioner\n",
-            "2024-01-15T00:14:49.2839497Z 2.0.321.1\n",
-            "2024-01-15T00:14:49.2839965Z ##[endgroup]\n",
-        );
+    #[wasm_bindgen(js_name = toNdjson)]
+    pub fn to_ndjson_js(&self) -> Result<String, JsError> {
+        self.lines_ndjson()
+            .map_err(|err| JsError::from(Error::from(err)))
+    }
 
-        let mut parser = Parser::new();
-        parser.set_raw(lines);
+    #[wasm_bindgen(js_name = searchSummary)]
+    pub fn search_summary_js(&self) -> Result<String, JsError> {
+        to_json(&self.search_summary(), false).map_err(|err| JsError::from(Error::from(err)))
+    }
 
-        assert_eq!(parser.lines.len(), 3);
+    #[wasm_bindgen(js_name = matchSnippets)]
+    pub fn match_snippets_js(&self, radius: usize) -> Result<String, JsError> {
+        to_json(&self.match_snippets(radius), false).map_err(|err| JsError::from(Error::from(err)))
+    }
 
-        let expected_children: Vec<usize> = vec![3, 4, 1];
-        for (i, line) in parser.lines.iter().enumerate() {
-            match &line.group {
-                Some(group) => {
-                    assert_eq!(group.children.len(), expected_children[i]);
-                }
-                _ => panic!("expected Node::Group"),
-            }
-        }
+    // the line with the number as json, or null if there isn't one
+    #[wasm_bindgen(js_name = getLine)]
+    pub fn get_line_js(&self, number: usize) -> Result<String, JsError> {
+        to_json(&self.get_line(number), false).map_err(|err| JsError::from(Error::from(err)))
     }
 
-    #[test]
-    fn weird_endgroup_behavior() {
-        let lines = concat!(
-            "##[group]start group\n",
-            "inside group\n",
-            "##[endgroup]\n",
-            "outside group\n",
+    // error and warning lines flattened out of their groups, each with its group's title (if any)
+    // as gt
+    #[wasm_bindgen(js_name = getErrors)]
+    pub fn get_errors_js(&self) -> Result<String, JsError> {
+        let errors: Vec<ErrorLine> = self
+            .errors()
+            .into_iter()
+            .map(|(line, group_title)| ErrorLine { line, group_title })
+            .collect();
+        to_json(&errors, false).map_err(|err| JsError::from(Error::from(err)))
+    }
+
+    #[wasm_bindgen(js_name = groupsOutline)]
+    pub fn groups_outline_js(&self) -> Result<String, JsError> {
+        to_json(&self.groups_outline(), false).map_err(|err| JsError::from(Error::from(err)))
+    }
+
+    // {line number: [[start, end], ..]} utf-16 highlight ranges as json, see highlight_ranges
+    #[wasm_bindgen(js_name = highlightRanges)]
+    pub fn highlight_ranges_js(&self) -> Result<String, JsError> {
+        to_json(&self.highlight_ranges(), false).map_err(|err| JsError::from(Error::from(err)))
+    }
+
+    // [first, last] unix ms timestamps as json, or null without any lines
+    #[wasm_bindgen(js_name = timeRange)]
+    pub fn time_range_js(&self) -> Result<String, JsError> {
+        to_json(&self.time_range(), false).map_err(|err| JsError::from(Error::from(err)))
+    }
+}
+
+// rust equivalents of the wasm exports that would otherwise return JS errors
+#[cfg(feature = "native")]
+impl Parser {
+    pub fn stringify(&self, pretty: bool) -> serde_json::Result<String> {
+        self.lines_json(pretty)
+    }
+
+    // like stringify, but writes straight to the writer instead of building a string
+    pub fn serialize_to_writer<W: std::io::Write>(
+        &self,
+        writer: W,
+        pretty: bool,
+    ) -> serde_json::Result<()> {
+        let lines = Shown(&self.lines);
+        if self.include_raw_maps {
+            let lines = with_raw_maps(&self.lines)?;
+            return if pretty {
+                serde_json::to_writer_pretty(writer, &lines)
+            } else {
+                serde_json::to_writer(writer, &lines)
+            };
+        }
+
+        if pretty {
+            serde_json::to_writer_pretty(writer, &lines)
+        } else {
+            serde_json::to_writer(writer, &lines)
+        }
+    }
+
+    // like stringify, but as a tree of {"type": "group", "header": {..}, "children": [..]} and
+    // {"type": "line", ..} nodes, where headers are serialized without their children
+    pub fn stringify_tree(&self, pretty: bool) -> serde_json::Result<String> {
+        self.lines_tree_json(pretty)
+    }
+
+    // loads lines serialized with stringify, see Parser::from_lines for what is restored
+    pub fn from_json(json: &str) -> serde_json::Result<Self> {
+        serde_json::from_str(json).map(Parser::from_lines)
+    }
+
+    // newline delimited json, one compact record per displayed line with group children following
+    // their header. Each record has a depth, 0 for top level lines and 1 for group children
+    pub fn to_ndjson(&self) -> serde_json::Result<String> {
+        self.lines_ndjson()
+    }
+
+    // appends the lines read from r without buffering the whole log, like add_line but lines keep
+    // their terminators (as with set_raw) and invalid utf-8 is replaced (as with set_raw_bytes)
+    pub fn append_from_reader<R: std::io::BufRead>(
+        &mut self,
+        mut r: R,
+    ) -> std::io::Result<Summary> {
+        let mut summary = Summary::default();
+        let mut buf = Vec::new();
+        while r.read_until(b'\n', &mut buf)? > 0 {
+            self.push_chunk(None, &String::from_utf8_lossy(&buf), &mut summary);
+            buf.clear();
+        }
+        Ok(summary)
+    }
+
+    // an empty format disables formatted timestamps, utc=false formats in the local timezone
+    pub fn set_timestamp_format(&mut self, fmt: &str, utc: bool) -> Result<(), Error> {
+        self.update_timestamp_format(fmt, utc)
+    }
+}
+
+impl Parser {
+    // a parser with previously serialized lines, e.g. a snapshot saved with stringify. The lines
+    // keep their elements as serialized and rebuild the rest from them (see Line::restore), so
+    // searching and adding lines work as usual. Options aren't serialized and start as defaults
+    #[cfg(any(feature = "wasm", feature = "native"))]
+    fn from_lines(mut lines: Vec<Line>) -> Self {
+        lines.iter_mut().for_each(Line::restore);
+
+        let mut parser = Self::new();
+        parser.idx = lines
+            .iter()
+            .flat_map(|line| line.iter())
+            .map(|line| line.number + 1)
+            .max()
+            .unwrap_or(1);
+        parser.seen_group = lines.iter().any(|line| line.group.is_some());
+        parser.lines = lines;
+        parser
+    }
+
+    #[cfg(any(feature = "wasm", feature = "native"))]
+    fn lines_json(&self, pretty: bool) -> serde_json::Result<String> {
+        if self.include_raw_maps {
+            to_json(&with_raw_maps(&self.lines)?, pretty)
+        } else {
+            to_json(&Shown(&self.lines), pretty)
+        }
+    }
+
+    #[cfg(any(feature = "wasm", feature = "native"))]
+    fn lines_tree_json(&self, pretty: bool) -> serde_json::Result<String> {
+        let record = |line: &Line| -> serde_json::Result<serde_json::Value> {
+            let mut record = line.to_record()?;
+            if self.include_raw_maps {
+                add_raw_maps(&mut record, line)?;
+            }
+            Ok(record)
+        };
+
+        let nodes = self
+            .lines
+            .iter()
+            .filter(|line| !line.hidden)
+            .map(|line| {
+                Ok(match &line.group {
+                    Some(group) => TreeNode::Group {
+                        header: record(line)?,
+                        children: group
+                            .children
+                            .iter()
+                            .filter(|child| !child.hidden)
+                            .map(|child| record(child).map(TreeNode::Line))
+                            .collect::<serde_json::Result<_>>()?,
+                    },
+                    None => TreeNode::Line(record(line)?),
+                })
+            })
+            .collect::<serde_json::Result<Vec<TreeNode>>>()?;
+
+        to_json(&nodes, pretty)
+    }
+
+    #[cfg(any(feature = "wasm", feature = "native"))]
+    fn lines_ndjson(&self) -> serde_json::Result<String> {
+        fn write_records(
+            out: &mut String,
+            line: &Line,
+            depth: usize,
+            raw_maps: bool,
+        ) -> serde_json::Result<()> {
+            let mut record = line.to_record()?;
+            if raw_maps {
+                add_raw_maps(&mut record, line)?;
+            }
+            if let Some(object) = record.as_object_mut() {
+                object.insert("depth".to_string(), depth.into());
+            }
+            out.push_str(&serde_json::to_string(&record)?);
+            out.push('\n');
+
+            if let Some(group) = &line.group {
+                for child in group.children.iter().filter(|child| !child.hidden) {
+                    write_records(out, child, depth + 1, raw_maps)?;
+                }
+            }
+            Ok(())
+        }
+
+        let mut out = String::new();
+        for line in self.lines.iter().filter(|line| !line.hidden) {
+            write_records(&mut out, line, 0, self.include_raw_maps)?;
+        }
+        Ok(out)
+    }
+
+    // the number of leading incoming lines that repeat the last added ones
+    fn overlap(&self, incoming: &[&str]) -> usize {
+        fn trim(raw: &str) -> &str {
+            raw.trim_end_matches(['\n', '\r'])
+        }
+
+        // the raw text of up to incoming.len() of the last added lines, including endgroups
+        let mut tail: Vec<&str> = Vec::new();
+        for line in self.lines.iter().rev() {
+            if let Some(group) = &line.group {
+                tail.extend(group.end_raw.as_deref().map(trim));
+                tail.extend(group.children.iter().rev().map(|child| trim(&child.raw)));
+            }
+            tail.push(trim(&line.raw));
+            if tail.len() >= incoming.len() {
+                break;
+            }
+        }
+        tail.truncate(incoming.len());
+        tail.reverse();
+
+        (1..=tail.len())
+            .rev()
+            .find(|&n| {
+                tail[tail.len() - n..]
+                    .iter()
+                    .zip(&incoming[..n])
+                    .all(|(added, raw)| *added == trim(raw))
+            })
+            .unwrap_or(0)
+    }
+
+    fn dimming(&self) -> bool {
+        self.dim_non_matches && (!self.search.is_empty() || !self.search_terms.is_empty())
+    }
+
+    fn update_dimming(&mut self, changed: &mut Vec<usize>) {
+        let dim = self.dimming();
+        for line in self.lines.iter_mut() {
+            line.set_dim_unmatched(dim, changed);
+        }
+    }
+
+    fn update_base_timestamp(&mut self, base: Option<i64>) {
+        self.base_ts = base;
+        for line in self.lines.iter_mut() {
+            line.set_elapsed(base);
+        }
+    }
+
+    #[cfg(any(feature = "wasm", feature = "native"))]
+    fn update_timestamp_format(&mut self, fmt: &str, utc: bool) -> Result<(), Error> {
+        self.ts_format = if fmt.is_empty() {
+            None
+        } else {
+            match TimestampFormat::new(fmt, utc) {
+                Some(format) => Some(format),
+                None => return Err(Error::InvalidTimestampFormat(fmt.to_string())),
+            }
+        };
+
+        for line in self.lines.iter_mut() {
+            line.format_ts(self.ts_format.as_ref());
+        }
+
+        Ok(())
+    }
+
+    // (line number, match count) for every line with matches, including group children
+    pub fn search_summary(&self) -> Vec<(usize, usize)> {
+        self.iter_display()
+            .filter(|line| !line.highlights.is_empty())
+            .map(|line| (line.number, line.highlights.len()))
+            .collect()
+    }
+
+    // line number -> sorted (start, end) utf-16 ranges of its highlights, for every line (including
+    // group children) with a match. Lets frontends draw highlights over already rendered text
+    // instead of re-rendering elements on every search
+    pub fn highlight_ranges(&self) -> BTreeMap<usize, Vec<(usize, usize)>> {
+        self.iter_display()
+            .filter(|line| !line.highlights.is_empty())
+            .map(|line| {
+                let mut ranges: Vec<(usize, usize)> = line.utf16_highlights().into_iter().collect();
+                ranges.sort_unstable();
+                (line.number, ranges)
+            })
+            .collect()
+    }
+
+    // (line number, snippet) around every match, including in group children, see
+    // Line::match_snippets
+    pub fn match_snippets(&self, radius: usize) -> Vec<(usize, String)> {
+        self.iter_display()
+            .flat_map(|line| {
+                line.match_snippets(radius)
+                    .into_iter()
+                    .map(move |snippet| (line.number, snippet))
+            })
+            .collect()
+    }
+
+    // plain text for archiving, each line prefixed with its timestamp formatted in utc with the
+    // strftime pattern. Group children are indented by two spaces. Lines without a timestamp in
+    // the log get no prefix rather than the time they were parsed at
+    pub fn to_plaintext_with_timestamps(&self, fmt: &str) -> Result<String, Error> {
+        let format = TimestampFormat::new(fmt, true)
+            .ok_or_else(|| Error::InvalidTimestampFormat(fmt.to_string()))?;
+
+        let plain = |line: &Line, indent: &str| match format.format(line.ts) {
+            Some(ts) if !line.ts_synthetic => format!("{}{} {}", indent, ts, line.content),
+            _ => format!("{}{}", indent, line.content),
+        };
+
+        let mut out = Vec::new();
+        for line in self.lines.iter().filter(|line| !line.hidden) {
+            out.push(plain(line, ""));
+            if let Some(group) = &line.group {
+                let children = group.children.iter().filter(|child| !child.hidden);
+                out.extend(children.map(|child| plain(child, "  ")));
+            }
+        }
+        Ok(out.join("\n"))
+    }
+
+    // the line (including group children) with the number, if it's still there
+    pub fn get_line(&self, number: usize) -> Option<&Line> {
+        let (idx, child) = self.find_line(number)?;
+        let line = &self.lines[idx];
+        match (child, &line.group) {
+            (Some(child), Some(group)) => group.children.get(child),
+            _ => Some(line),
+        }
+    }
+
+    // (index into lines, index into its group's children if it's a child) of the line with the
+    // number. Numbers outside of what's been assigned are rejected up front, the rest is a binary
+    // search since numbers only increase
+    fn find_line(&self, number: usize) -> Option<(usize, Option<usize>)> {
+        if number == 0 || number >= self.idx {
+            return None;
+        }
+
+        let idx = self
+            .lines
+            .partition_point(|line| line.number <= number)
+            .checked_sub(1)?;
+        let line = &self.lines[idx];
+        if line.number == number {
+            return Some((idx, None));
+        }
+
+        let children = &line.group.as_ref()?.children;
+        let child = children
+            .binary_search_by_key(&number, |child| child.number)
+            .ok()?;
+        Some((idx, Some(child)))
+    }
+
+    // (line, title of the group it's in) for every error and warning line, including group children
+    pub fn errors(&self) -> Vec<(&Line, Option<&str>)> {
+        let mut errors = Vec::new();
+        for line in &self.lines {
+            if line.is_error() || line.is_warning() {
+                errors.push((line, None));
+            }
+
+            if let Some(group) = &line.group {
+                errors.extend(
+                    group
+                        .children
+                        .iter()
+                        .filter(|child| child.is_error() || child.is_warning())
+                        .map(|child| (child, line.group_title())),
+                );
+            }
+        }
+        errors
+    }
+
+    // (min, max) timestamp across all lines, including group children. Synthesized timestamps can be
+    // out of order, so every line is checked rather than just the first and last
+    pub fn time_range(&self) -> Option<(i64, i64)> {
+        self.lines
+            .iter()
+            .flat_map(|line| line.iter())
+            .map(|line| line.ts)
+            .fold(None, |range, ts| match range {
+                Some((min, max)) => Some((ts.min(min), ts.max(max))),
+                None => Some((ts, ts)),
+            })
+    }
+
+    // (line number, title, child count) for every group header, groups can't nest so there's no depth
+    pub fn groups_outline(&self) -> Vec<(usize, String, usize)> {
+        self.lines
+            .iter()
+            .filter_map(|line| {
+                let group = line.group.as_ref()?;
+                let title = line.group_title()?.to_string();
+                Some((line.number, title, group.children.len()))
+            })
+            .collect()
+    }
+
+    // numbers of lines (including group children) that are new or whose content, command, elements or
+    // group state differ from the line with the same number in previous. For a log that was only
+    // appended to, this is just the new lines, plus the last group's header if the new lines closed it
+    pub fn diff(&self, previous: &Parser) -> Vec<usize> {
+        let previous: HashMap<usize, &Line> = previous
+            .iter_display()
+            .map(|line| (line.number, line))
+            .collect();
+
+        let group_state = |line: &Line| {
+            line.group
+                .as_ref()
+                .map(|group| (group.ended, group.collapsed))
+        };
+
+        self.iter_display()
+            .filter(|line| match previous.get(&line.number) {
+                Some(prev) => {
+                    prev.content != line.content
+                        || prev.cmd != line.cmd
+                        || prev.elements != line.elements
+                        || group_state(prev) != group_state(line)
+                }
+                None => true,
+            })
+            .map(|line| line.number)
+            .collect()
+    }
+
+    // iterates over all lines, with group children right after their group's header. Debug lines
+    // are skipped when they're hidden with set_show_debug
+    pub fn iter_display(&self) -> impl Iterator<Item = &Line> {
+        self.lines
+            .iter()
+            .flat_map(|line| line.iter())
+            .filter(|line| !line.hidden)
+    }
+}
+
+// a node of stringifyTree, lines are serialized without their group's children
+#[cfg(any(feature = "wasm", feature = "native"))]
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum TreeNode {
+    Line(serde_json::Value),
+    Group {
+        header: serde_json::Value,
+        children: Vec<TreeNode>,
+    },
+}
+
+// a line as serialized by getErrors
+#[cfg(feature = "wasm")]
+#[derive(Serialize)]
+struct ErrorLine<'a> {
+    #[serde(flatten)]
+    line: &'a Line,
+    #[serde(rename = "gt", skip_serializing_if = "Option::is_none")]
+    group_title: Option<&'a str>,
+}
+
+// serializes lines with their (otherwise skipped) links, ansis and highlights maps added, keyed by
+// byte index into content. Links and highlights are also added keyed by utf-16 index
+#[cfg(any(feature = "wasm", feature = "native"))]
+fn with_raw_maps(lines: &[Line]) -> serde_json::Result<serde_json::Value> {
+    let mut value = serde_json::to_value(Shown(lines))?;
+    if let Some(values) = value.as_array_mut() {
+        for (line, value) in lines.iter().filter(|line| !line.hidden).zip(values) {
+            add_raw_maps(value, line)?;
+        }
+    }
+
+    Ok(value)
+}
+
+#[cfg(any(feature = "wasm", feature = "native"))]
+fn add_raw_maps(value: &mut serde_json::Value, line: &Line) -> serde_json::Result<()> {
+    if let Some(object) = value.as_object_mut() {
+        object.insert("links".to_string(), serde_json::to_value(&line.links)?);
+        object.insert("ansis".to_string(), serde_json::to_value(&line.ansis)?);
+        object.insert(
+            "highlights".to_string(),
+            serde_json::to_value(&line.highlights)?,
+        );
+        // the same maps with utf-16 indices, so js can slice content directly
+        object.insert(
+            "links_utf16".to_string(),
+            serde_json::to_value(line.utf16_links())?,
+        );
+        object.insert(
+            "highlights_utf16".to_string(),
+            serde_json::to_value(line.utf16_highlights())?,
+        );
+    }
+
+    // indexing with value["group"] would insert nulls into records without children
+    if let (Some(group), Some(children)) = (
+        &line.group,
+        value
+            .pointer_mut("/group/children")
+            .and_then(|children| children.as_array_mut()),
+    ) {
+        let shown = group.children.iter().filter(|child| !child.hidden);
+        for (child, value) in shown.zip(children) {
+            add_raw_maps(value, child)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn is_blank(line: &Line) -> bool {
+    line.cmd.is_none() && line.content.trim().is_empty()
+}
+
+#[cfg(any(feature = "wasm", feature = "native"))]
+fn to_json<T: Serialize + ?Sized>(value: &T, pretty: bool) -> serde_json::Result<String> {
+    if pretty {
+        serde_json::to_string_pretty(value)
+    } else {
+        serde_json::to_string(value)
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::useless_vec)]
+mod tests {
+    use super::*;
+    use crate::ansi::ANSISequence;
+    use crate::element::Element;
+    use crate::style::Color;
+    use std::collections::HashMap;
+    use std::vec;
+
+    #[test]
+    fn normal() {
+        let lines = concat!(
+        "2024-01-15T00:14:43.5805748Z Requested labels: ubuntu-latest\n",
+        "2024-01-15T00:14:43.5806028Z Job defined at: robherley/tmp/.github/workflows/blank.yml@refs/heads/main\n",
+        "2024-01-15T00:14:43.5806125Z Waiting for a runner to pick up this job...\n",
+        "2024-01-15T00:14:44.2854453Z Job is waiting for a hosted runner to come online.\n",
+        "2024-01-15T00:14:46.5843551Z Job is about to start running on the hosted runner: GitHub Actions 2 (hosted)\n",
+        "2024-01-15T00:14:49.2802822Z Current runner version: '2.311.0'\n",
+        );
+
+        let mut parser = Parser::new();
+        parser.set_raw(lines);
+
+        assert_eq!(parser.lines.len(), 6);
+
+        for (i, line) in parser.lines.iter().enumerate() {
+            assert_eq!(line.number, i + 1);
+            assert_eq!(line.cmd, None);
+        }
+    }
+
+    #[test]
+    fn with_groups() {
+        let lines = concat!(
+            "2024-01-15T00:14:49.2830954Z ##[group]Operating System\n",
+            "2024-01-15T00:14:49.2831846Z Ubuntu\n",
+            "2024-01-15T00:14:49.2832204Z 22.04.3\n",
+            "2024-01-15T00:14:49.2832638Z LTS\n",
+            "2024-01-15T00:14:49.2833085Z ##[endgroup]\n",
+            "2024-01-15T00:14:49.2833509Z ##[group]Runner Image\n",
+            "2024-01-15T00:14:49.2834023Z Image: ubuntu-22.04\n",
+            "2024-01-15T00:14:49.2834552Z Version: 20240107.1.0\n",
+            "2024-01-15T00:14:49.2835705Z Included Software: https://github.com/actions/runner-images/blob/ubuntu22/20240107.1/images/ubuntu/Ubuntu2204-Readme.md\n",
+            "2024-01-15T00:14:49.2837409Z Image Release: https://github.com/actions/runner-images/releases/tag/ubuntu22%2F20240107.1\n",
+            "2024-01-15T00:14:49.2838476Z ##[endgroup]\n",
+            "2024-01-15T00:14:49.2838965Z ##[group]Runner Image Provisioner\n",
+            "2024-01-15T00:14:49.2839497Z 2.0.321.1\n",
+            "2024-01-15T00:14:49.2839965Z ##[endgroup]\n",
+        );
+
+        let mut parser = Parser::new();
+        parser.set_raw(lines);
+
+        assert_eq!(parser.lines.len(), 3);
+
+        let expected_children: Vec<usize> = vec![3, 4, 1];
+        for (i, line) in parser.lines.iter().enumerate() {
+            match &line.group {
+                Some(group) => {
+                    assert_eq!(group.children.len(), expected_children[i]);
+                }
+                _ => panic!("expected Node::Group"),
+            }
+        }
+    }
+
+    // three groups with 3, 4 and 1 children, headers on lines 1, 5 and 10
+    const GROUPS: &str = concat!(
+        "##[group]Operating System\n",
+        "Ubuntu\n",
+        "22.04.3\n",
+        "LTS\n",
+        "##[endgroup]\n",
+        "##[group]Runner Image\n",
+        "Image: ubuntu-22.04\n",
+        "Version: 20240107.1.0\n",
+        "Included Software: https://github.com/actions/runner-images\n",
+        "Image Release: https://github.com/actions/runner-images/releases\n",
+        "##[endgroup]\n",
+        "##[group]Runner Image Provisioner\n",
+        "2.0.321.1\n",
+        "##[endgroup]\n",
+    );
+
+    #[test]
+    fn summary() {
+        let mut parser = Parser::new();
+        let summary = parser.set_raw(GROUPS);
+
+        assert_eq!(
+            summary,
+            Summary {
+                lines: parser.iter_display().count(),
+                groups: parser.groups_outline().len(),
+                errors: parser.error_count(),
+                warnings: parser.warning_count(),
+            }
+        );
+        assert_eq!(summary.lines, 11);
+        assert_eq!(summary.groups, 3);
+    }
+
+    #[test]
+    fn groups_outline() {
+        let mut parser = Parser::new();
+        parser.set_raw(GROUPS);
+
+        assert_eq!(
+            parser.groups_outline(),
+            vec![
+                (1, "Operating System".to_string(), 3),
+                (5, "Runner Image".to_string(), 4),
+                (10, "Runner Image Provisioner".to_string(), 1),
+            ]
+        );
+
+        parser.set_raw("no groups\nhere\n");
+        assert!(parser.groups_outline().is_empty());
+    }
+
+    #[test]
+    fn find_group() {
+        let mut parser = Parser::new();
+        parser.set_raw(GROUPS);
+
+        assert_eq!(parser.find_group("Runner Image"), Some(5));
+        assert_eq!(parser.find_group("  runner image provisioner "), Some(10));
+        assert_eq!(parser.find_group("Runner"), None);
+
+        parser.set_raw("no groups\nhere\n");
+        assert_eq!(parser.find_group("here"), None);
+    }
+
+    #[test]
+    #[cfg(feature = "native")]
+    fn tree() {
+        let mut parser = Parser::new();
+        parser.set_raw(GROUPS);
+
+        let tree: serde_json::Value =
+            serde_json::from_str(&parser.stringify_tree(false).unwrap()).unwrap();
+        let nodes = tree.as_array().unwrap();
+        assert_eq!(nodes.len(), 3);
+        for (node, (line, expected)) in nodes.iter().zip(parser.lines.iter().zip([3, 4, 1])) {
+            assert_eq!(node["type"], "group");
+            assert_eq!(node["header"]["n"], line.number);
+            assert!(node["header"]["group"].get("children").is_none());
+
+            let children = node["children"].as_array().unwrap();
+            assert_eq!(children.len(), expected);
+            assert!(children.iter().all(|child| child["type"] == "line"));
+        }
+        assert_eq!(nodes[1]["children"][0]["n"], 6);
+    }
+
+    #[test]
+    #[cfg(any(feature = "wasm", feature = "native"))]
+    fn styled_group_title() {
+        let mut parser = Parser::new();
+        parser.set_raw("##[group]\u{1b}[1mBuild\u{1b}[0m step\ninside\n##[endgroup]\n");
+
+        let header = &parser.lines[0];
+        assert_eq!(header.cmd, Some(Command::Group));
+        assert_eq!(header.content, "Build step");
+        assert_eq!(
+            to_json(&header.elements, false).unwrap(),
+            r#"[{"type":"text","content":"Build","styles":{"b":true}}," step"]"#
+        );
+        assert_eq!(parser.find_group("build step"), Some(1));
+    }
+
+    #[test]
+    fn collapse_blank_lines() {
+        let lines = concat!(
+            "one\n",
+            "\n",
+            "\n",
+            "  \n",
+            "two\n",
+            "##[group]group\n",
+            "\n",
+            "\n",
+            "three\n",
+            "\n",
+            "##[endgroup]\n",
+            "\n",
+            "\n",
+        );
+
+        let mut parser = Parser::new();
+        parser.set_raw(lines);
+        assert_eq!(parser.lines.len(), 8);
+        assert_eq!(parser.lines[5].group.as_ref().unwrap().children.len(), 4);
+
+        parser.set_collapse_blank_lines(true);
+        parser.set_raw(lines);
+        let contents: Vec<&str> = parser
+            .iter_display()
+            .map(|line| line.content.as_str())
+            .collect();
+        assert_eq!(
+            contents,
+            vec!["one", "", "two", "group", "", "three", "", ""]
+        );
+    }
+
+    #[test]
+    #[cfg(any(feature = "wasm", feature = "native"))]
+    fn include_raw_maps() {
+        let mut parser = Parser::new();
+        parser.set_raw("##[group]\u{1b}[1mgroup\ninside https://reb.gg\n##[endgroup]\n");
+        parser.set_search("in");
+
+        let json: serde_json::Value =
+            serde_json::from_str(&parser.lines_json(false).unwrap()).unwrap();
+        assert!(json[0].get("links").is_none());
+        assert!(json[0].get("ansis").is_none());
+        assert!(json[0].get("highlights").is_none());
+
+        parser.set_include_raw_maps(true);
+        let json: serde_json::Value =
+            serde_json::from_str(&parser.lines_json(false).unwrap()).unwrap();
+        assert_eq!(json[0]["ansis"], serde_json::json!({"0": ["Bold"]}));
+        assert_eq!(json[0]["links"], serde_json::json!({}));
+
+        let child = &json[0]["group"]["children"][0];
+        assert_eq!(child["links"], serde_json::json!({"7": [21, "Url"]}));
+        assert_eq!(child["highlights"], serde_json::json!({"0": 2}));
+        assert_eq!(child["links_utf16"], serde_json::json!({"7": [21, "Url"]}));
+        assert_eq!(child["highlights_utf16"], serde_json::json!({"0": 2}));
+        assert_eq!(child["elements"].as_array().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn weird_endgroup_behavior() {
+        let lines = concat!(
+            "##[group]start group\n",
+            "inside group\n",
+            "##[endgroup]\n",
+            "outside group\n",
             "##[group]start another group\n",
             "inside another group\n",
             "##[endgroup]\n",
@@ -195,61 +1640,1505 @@ mod tests {
         let mut parser = Parser::new();
         parser.set_raw(lines);
 
-        assert_eq!(parser.lines.len(), 5);
+        assert_eq!(parser.lines.len(), 5);
+
+        let expected_group = vec![true, false, true, false, false];
+        for (i, line) in parser.lines.iter().enumerate() {
+            match line.group {
+                Some(_) => {
+                    assert!(expected_group[i], "expected group");
+                }
+                _ => assert!(!expected_group[i], "did not expect group"),
+            }
+        }
+
+        // since the last two endgroups are not closing any groups, they are rendered as regular lines
+        parser.lines[3..].iter().for_each(|line| match line.cmd {
+            Some(Command::EndGroup) => {}
+            _ => panic!("expected Command::EndGroup"),
+        });
+    }
+
+    #[test]
+    fn tolerant_groups() {
+        let lines = concat!(
+            "tail of a group we never saw\n",
+            "##[endgroup]\n",
+            "##[group]start group\n",
+            "inside group\n",
+            "##[endgroup]\n",
+            "##[endgroup]\n",
+        );
+
+        let mut parser = Parser::new();
+        parser.set_raw(lines);
+        assert_eq!(parser.lines.len(), 4);
+        assert_eq!(parser.lines[1].cmd, Some(Command::EndGroup));
+
+        parser.set_tolerant_groups(true);
+        parser.set_raw(lines);
+        assert_eq!(parser.lines.len(), 3);
+        assert_eq!(parser.lines[0].number, 1);
+        assert_eq!(parser.lines[1].number, 2);
+        assert!(parser.lines[1].group.is_some());
+        // orphans after a group has been seen are still shown
+        assert_eq!(parser.lines[2].cmd, Some(Command::EndGroup));
+    }
+
+    #[test]
+    fn search() {
+        let lines = concat!("foo\n", "bar\n", "baz\n");
+
+        let mut parser = Parser::new();
+        parser.set_raw(lines);
+
+        let find_matches = |parser: &Parser| -> Vec<bool> {
+            parser.lines.iter().map(|line| line.matches() > 0).collect()
+        };
+
+        assert_eq!(parser.lines.len(), 3);
+        assert_eq!(find_matches(&parser), vec![false, false, false]);
+
+        parser.set_search("bar");
+        assert_eq!(parser.matches(), 1);
+        assert_eq!(find_matches(&parser), vec![false, true, false]);
+        match parser.lines.get(1) {
+            Some(line) => assert_eq!(line.highlights, HashMap::from([(0, 3)])),
+            _ => panic!("expected line at index 1"),
+        }
+
+        parser.add_line("", "----> bar <----");
+        assert_eq!(parser.matches(), 2);
+        assert_eq!(find_matches(&parser), vec![false, true, false, true]);
+        match parser.lines.get(3) {
+            Some(line) => assert_eq!(line.highlights, HashMap::from([(6, 9)])),
+            _ => panic!("expected line at index 3"),
+        }
+
+        parser.add_line("", "#[group]some group");
+        parser.add_line("", "baz bar baz");
+        parser.add_line("", "#[endgroup]");
+        assert_eq!(parser.matches(), 3);
+
+        parser.set_search("");
+        assert_eq!(parser.matches(), 0);
+    }
+
+    #[test]
+    #[cfg(any(feature = "wasm", feature = "native"))]
+    fn search_terms() {
+        let mut parser = Parser::new();
+        parser.set_raw("error: boom\nwarning: careful\nall good\n");
+        parser.set_search("good");
+        assert_eq!(parser.matches(), 1);
+
+        parser.set_search_terms(vec!["Error".to_string(), "warning".to_string()]);
+        assert_eq!(parser.search, "");
+        assert_eq!(parser.matches(), 2);
+        assert_eq!(parser.lines[0].highlight_groups, HashMap::from([(0, 0)]));
+        assert_eq!(parser.lines[1].highlight_groups, HashMap::from([(0, 1)]));
+        assert!(parser.lines[2].highlights.is_empty());
+
+        parser.add_line("", "another warning");
+        assert_eq!(parser.lines[3].highlight_groups, HashMap::from([(8, 1)]));
+        assert_eq!(
+            to_json(&parser.lines[3].elements, false).unwrap(),
+            r#"["another ",{"type":"text","content":"warning","styles":{"hl":true,"hg":1}}]"#
+        );
+
+        parser.clear_search();
+        assert_eq!(parser.matches(), 0);
+        assert!(parser.lines[0].highlight_groups.is_empty());
+    }
+
+    #[test]
+    fn search_survives_set_raw() {
+        let mut parser = Parser::new();
+        parser.set_raw("foo\nbar\n");
+        parser.set_search("bar");
+        assert_eq!(parser.matches(), 1);
+
+        // the search is re-applied to the new content, not the old lines
+        parser.set_raw("baz\nbar bar\nfoo\n");
+        assert_eq!(parser.search, "bar");
+        assert_eq!(parser.matches(), 2);
+        assert_eq!(parser.lines[1].highlights, HashMap::from([(0, 3), (4, 7)]));
+
+        parser.add_line("", "bar");
+        assert_eq!(parser.matches(), 3);
+        assert_eq!(parser.search_summary(), vec![(2, 2), (4, 1)]);
+    }
+
+    #[test]
+    fn raw_text() {
+        let inputs = [
+            concat!(
+                "2024-01-15T00:14:49.2830954Z ##[group]Operating System\n",
+                "2024-01-15T00:14:49.2831846Z \u{1b}[1mUbuntu\u{1b}[0m\n",
+                "2024-01-15T00:14:49.2833085Z ##[endgroup]\n",
+                "2024-01-15T00:14:49.2833509Z https://reb.gg\n",
+                "##[endgroup]\n",
+            ),
+            "no trailing newline\r\n\r\nfoo",
+            "",
+        ];
+
+        for input in inputs {
+            let mut parser = Parser::new();
+            parser.set_raw(input);
+            assert_eq!(parser.raw_text(), input);
+        }
+
+        let mut parser = Parser::new();
+        parser.add_line("", "foo");
+        parser.add_line("", "");
+        parser.add_line("", "bar");
+        assert_eq!(parser.raw_text(), "foo\n\nbar");
+    }
+
+    #[test]
+    #[cfg(feature = "native")]
+    fn timestamp_format() {
+        let mut parser = Parser::new();
+        parser.add_line("", "2024-01-15T00:14:43.5805748Z from prefix");
+        parser.add_line("1705277683580-0", "from id");
+        assert!(parser.lines.iter().all(|line| line.ts_fmt.is_none()));
+
+        parser
+            .set_timestamp_format("%Y-%m-%dT%H:%M:%S%.3fZ", true)
+            .unwrap();
+        parser.add_line("1705277683580-1", "after set");
+
+        assert_eq!(parser.lines.len(), 3);
+        for line in parser.lines.iter() {
+            assert_eq!(line.ts_fmt, Some("2024-01-15T00:14:43.580Z".to_string()));
+        }
+
+        parser.set_timestamp_format("%d/%m/%Y %H:%M", true).unwrap();
+        assert_eq!(parser.lines[0].ts_fmt, Some("15/01/2024 00:14".to_string()));
+
+        let json = parser.stringify(false).unwrap();
+        assert!(json.contains(r#""tf":"15/01/2024 00:14""#));
+
+        parser.set_timestamp_format("", true).unwrap();
+        assert!(parser.lines.iter().all(|line| line.ts_fmt.is_none()));
+
+        assert_eq!(
+            parser.set_timestamp_format("%Q", true),
+            Err(Error::InvalidTimestampFormat("%Q".to_string()))
+        );
+    }
+
+    #[test]
+    fn infer_levels() {
+        let lines = concat!(
+            "ERROR: boom\n",
+            "warning: careful\n",
+            "##[debug]ERROR: real\n"
+        );
+
+        let mut parser = Parser::new();
+        parser.set_raw(lines);
+        assert!(parser.lines.iter().take(2).all(|line| line.cmd.is_none()));
+
+        parser.set_infer_levels(true);
+        parser.set_raw(lines);
+
+        let cmds: Vec<Option<Command>> = parser.lines.iter().map(|line| line.cmd).collect();
+        assert_eq!(
+            cmds,
+            vec![
+                Some(Command::Error),
+                Some(Command::Warning),
+                Some(Command::Debug)
+            ]
+        );
+    }
+
+    #[test]
+    fn search_diff() {
+        let lines = concat!(
+            "foo\n",
+            "bar\n",
+            "foo bar\n",
+            "baz\n",
+            "##[group]group\n",
+            "bar\n",
+            "foo\n",
+        );
+
+        let mut parser = Parser::new();
+        parser.set_raw(lines);
+
+        assert_eq!(parser.set_search_diff("foo"), vec![1, 3, 7]);
+        assert_eq!(parser.set_search_diff("bar"), vec![1, 2, 3, 6, 7]);
+        assert_eq!(parser.set_search_diff("bar"), Vec::<usize>::new());
+        assert_eq!(parser.set_search_diff(""), vec![2, 3, 6]);
+    }
+
+    #[test]
+    fn reset() {
+        let mut parser = Parser::new();
+        parser.set_raw("foo\nbar\n");
+        parser.set_search("bar");
+        assert_eq!(parser.matches(), 1);
+
+        parser.reset();
+        assert!(parser.lines.is_empty());
+        assert_eq!(parser.search, "");
+
+        parser.add_line("", "bar");
+        assert_eq!(parser.lines[0].number, 1);
+        assert!(parser.lines[0].highlights.is_empty());
+    }
+
+    #[test]
+    fn clear_search() {
+        let lines = concat!("foo\n", "bar\n", "##[group]group\n", "bar in group\n");
+
+        let mut parser = Parser::new();
+        parser.set_raw(lines);
+
+        let untouched = parser.lines[0].elements.as_ptr();
+        parser.set_search("bar");
+        assert_eq!(parser.matches(), 2);
+        assert_eq!(parser.lines[0].elements.as_ptr(), untouched);
+
+        parser.clear_search();
+        assert_eq!(parser.search, "");
+        assert_eq!(parser.matches(), 0);
+        assert_eq!(parser.lines[0].elements.as_ptr(), untouched);
+
+        let cleared = parser.lines[1].elements.as_ptr();
+        parser.clear_search();
+        assert_eq!(parser.lines[1].elements.as_ptr(), cleared);
+    }
+
+    #[test]
+    #[cfg(any(feature = "wasm", feature = "native"))]
+    fn group_collapsed() {
+        let lines = concat!(
+            "##[group]plain\n",
+            "foo\n",
+            "##[endgroup]\n",
+            "##[group]with error\n",
+            "##[error]bar\n",
+            "##[endgroup]\n",
+            "##[group]streaming\n",
+            "baz\n",
+        );
+
+        let collapsed = |parser: &Parser| -> Vec<bool> {
+            parser
+                .lines
+                .iter()
+                .map(|line| line.group.as_ref().unwrap().collapsed)
+                .collect()
+        };
+
+        let mut parser = Parser::new();
+        parser.set_raw(lines);
+        assert_eq!(collapsed(&parser), vec![true, false, false]);
+
+        let json = parser.lines_json(false).unwrap();
+        assert!(json.contains(r#""ended":true,"c":true"#));
+
+        parser.set_default_group_collapsed(false);
+        parser.set_raw(lines);
+        assert_eq!(collapsed(&parser), vec![false, false, false]);
+    }
+
+    #[test]
+    #[cfg(any(feature = "wasm", feature = "native"))]
+    fn manual_group() {
+        let header = "2024-01-15T00:14:43.0000000Z ##[group]Build";
+        let child = "2024-01-15T00:14:44.0000000Z compiling";
+
+        let mut parser = Parser::new();
+        parser.set_raw(&format!("{}\n{}\n", header, child));
+
+        // a plain line becomes a group header once it has a child
+        let mut manual = Line::new(1, None, "2024-01-15T00:14:43.0000000Z Build");
+        manual.add_child(Line::new(2, None, child));
+        assert_eq!(manual.cmd, Some(Command::Group));
+        assert_eq!(
+            to_json(&vec![manual.clone()], false).unwrap(),
+            to_json(&parser.lines, false).unwrap()
+        );
+
+        parser.add_line("", "2024-01-15T00:14:45.0000000Z ##[endgroup]");
+        manual.end_group(true);
+        assert_eq!(
+            to_json(&vec![manual], false).unwrap(),
+            to_json(&parser.lines, false).unwrap()
+        );
+    }
+
+    #[test]
+    #[cfg(any(feature = "wasm", feature = "native"))]
+    fn set_group_collapsed() {
+        let lines = concat!(
+            "##[group]plain\n",
+            "foo\n",
+            "##[endgroup]\n",
+            "bar\n",
+            "##[group]streaming\n",
+            "baz\n",
+        );
+
+        let mut parser = Parser::new();
+        parser.set_raw(lines);
+
+        let serialized = |parser: &Parser| -> serde_json::Value {
+            let json: serde_json::Value =
+                serde_json::from_str(&to_json(&parser.lines, false).unwrap()).unwrap();
+            json[0]["group"]["c"].clone()
+        };
+        assert_eq!(serialized(&parser), true);
+
+        assert!(parser.set_group_collapsed(1, false));
+        assert_eq!(serialized(&parser), false);
+        assert!(parser.set_group_collapsed(1, true));
+        assert_eq!(serialized(&parser), true);
+
+        // a child, a line outside of a group, a streaming group and a missing line
+        for number in [2, 3, 4, 0, 99] {
+            assert!(!parser.set_group_collapsed(number, true), "{}", number);
+        }
+        assert!(!parser.lines[2].group.as_ref().unwrap().collapsed);
+    }
+
+    #[test]
+    fn split_escape() {
+        let mut parser = Parser::new();
+        parser.add_line("", "foo\u{1b}[3");
+        parser.add_line("", "1mred\u{1b}[0m");
+
+        assert_eq!(parser.lines[0].content, "foo");
+        assert_eq!(parser.lines[1].content, "red");
+        assert_eq!(
+            parser.lines[1].ansis.get(&0),
+            Some(&vec![ANSISequence::SetFG4(1)])
+        );
+        assert_eq!(parser.raw_text(), "foo\u{1b}[3\n1mred\u{1b}[0m");
+
+        parser.add_line("", "bar\u{1b}[");
+        assert_eq!(parser.lines[2].content, "bar");
+        parser.flush();
+        assert_eq!(parser.lines.len(), 4);
+        assert_eq!(parser.lines[3].content, "\u{1b}[");
+        assert_eq!(parser.raw_text(), "foo\u{1b}[3\n1mred\u{1b}[0m\nbar\u{1b}[");
+        parser.flush();
+        assert_eq!(parser.lines.len(), 4);
+
+        // the carried escape is spliced in after the next line's timestamp and command
+        let mut parser = Parser::new();
+        parser.add_line("", "2024-01-15T00:14:43.0000000Z foo\u{1b}[3");
+        parser.add_line("", "2024-01-15T00:14:44.0000000Z ##[error]1mred");
+        assert_eq!(parser.lines[1].ts, 1705277684000);
+        assert_eq!(parser.lines[1].cmd, Some(Command::Error));
+        assert_eq!(parser.lines[1].content, "red");
+        assert_eq!(
+            parser.lines[1].ansis.get(&0),
+            Some(&vec![ANSISequence::SetFG4(1)])
+        );
+    }
+
+    #[test]
+    fn crlf() {
+        let mut parser = Parser::new();
+        parser.set_raw("foo\r\nbar\r\n");
+        parser.add_line("", "baz\r");
+
+        let content: Vec<&str> = parser.lines.iter().map(|l| l.content.as_str()).collect();
+        assert_eq!(content, vec!["foo", "bar", "baz"]);
+    }
+
+    #[test]
+    fn raw_bytes() {
+        let raw: &[u8] = b"foo\nbad \xff\xfe bytes\nbar\n";
+
+        let mut parser = Parser::new();
+        parser.set_raw_bytes(raw);
+
+        let content: Vec<&str> = parser.lines.iter().map(|l| l.content.as_str()).collect();
+        assert_eq!(content, vec!["foo", "bad \u{FFFD}\u{FFFD} bytes", "bar"]);
+    }
+
+    #[test]
+    #[cfg(any(feature = "wasm", feature = "native"))]
+    fn search_summary() {
+        let lines = concat!(
+            "foo bar foo\n",
+            "bar\n",
+            "##[group]foo group\n",
+            "no match\n",
+            "foo\n",
+            "##[endgroup]\n",
+        );
+
+        let mut parser = Parser::new();
+        parser.set_raw(lines);
+        assert!(parser.search_summary().is_empty());
+
+        parser.set_search("foo");
+        assert_eq!(parser.search_summary(), vec![(1, 2), (3, 1), (5, 1)]);
+        assert_eq!(
+            to_json(&parser.search_summary(), false).unwrap(),
+            "[[1,2],[3,1],[5,1]]"
+        );
+
+        parser.set_search("");
+        assert!(parser.search_summary().is_empty());
+    }
+
+    #[test]
+    #[cfg(feature = "native")]
+    fn serialize_to_writer() {
+        let lines = concat!(
+            "##[group]some group\n",
+            "\u{1b}[1mbold\u{1b}[0m https://reb.gg\n",
+            "##[endgroup]\n",
+            "foo\n",
+        );
+
+        let mut parser = Parser::new();
+        parser.set_raw(lines);
+
+        for pretty in [false, true] {
+            let mut buf: Vec<u8> = Vec::new();
+            parser.serialize_to_writer(&mut buf, pretty).unwrap();
+            assert_eq!(buf, parser.stringify(pretty).unwrap().into_bytes());
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "native")]
+    fn append_from_reader() {
+        let lines = concat!(
+            "2024-01-15T00:14:43.0000000Z ##[group]Build\n",
+            "2024-01-15T00:14:43.0000000Z \u{1b}[1mcompiling\u{1b}[0m https://reb.gg\n",
+            "2024-01-15T00:14:43.0000000Z ##[endgroup]\n",
+            "2024-01-15T00:14:44.0000000Z ##[error]\u{1b}[31mfailed\u{1b}[0m\n",
+            "2024-01-15T00:14:44.0000000Z no newline",
+        );
+
+        let mut want = Parser::new();
+        let want_summary = want.set_raw(lines);
+
+        let mut got = Parser::new();
+        let summary = got
+            .append_from_reader(std::io::Cursor::new(lines.as_bytes()))
+            .unwrap();
+
+        assert_eq!(summary, want_summary);
+        assert_eq!(
+            to_json(&got.lines, false).unwrap(),
+            to_json(&want.lines, false).unwrap()
+        );
+        assert_eq!(got.raw_text(), lines);
+
+        // appends after what's already there, carrying a dangling escape from the last line over
+        let summary = got
+            .append_from_reader(std::io::Cursor::new("more\n\u{1b}[3"))
+            .unwrap();
+        assert_eq!(summary.lines, 2);
+        got.add_line("", "1mred");
+        assert_eq!(got.lines.last().unwrap().content, "red");
+        assert_eq!(got.lines.last().unwrap().number, 7);
+    }
+
+    #[test]
+    fn detect_emails() {
+        let lines = "Author: Rob <foo@example.com> https://github.com/robherley\n";
+
+        let mut parser = Parser::new();
+        parser.set_raw(lines);
+        assert_eq!(parser.lines[0].links.len(), 1);
+
+        parser.set_detect_emails(true);
+        parser.set_raw(lines);
+        assert_eq!(parser.lines[0].links.len(), 2);
+    }
+
+    #[test]
+    fn line_accessors() {
+        let lines = concat!(
+            "2024-01-15T00:14:43.0000000Z ##[group]Build\n",
+            "2024-01-15T00:14:43.0000000Z foo bar\n",
+            "2024-01-15T00:14:43.0000000Z baz foo\n",
+            "2024-01-15T00:14:43.0000000Z ##[endgroup]\n",
+            "2024-01-15T00:14:44.0000000Z last foo\n",
+        );
+
+        let mut parser = Parser::new();
+        parser.set_raw(lines);
+        parser.set_search("foo");
+        let max = 4;
+
+        // 0 and anything past the last number are rejected, including numbers that would overflow
+        for number in [0, max + 1, usize::MAX] {
+            assert!(parser.get_line(number).is_none(), "{}", number);
+            assert!(!parser.update_line(number, "x"), "{}", number);
+            assert!(!parser.remove_line(number), "{}", number);
+        }
+        assert_eq!(parser.matches(), 3);
+
+        assert_eq!(parser.get_line(1).unwrap().content, "Build");
+        assert_eq!(parser.get_line(3).unwrap().content, "baz foo");
+        assert_eq!(parser.get_line(max).unwrap().content, "last foo");
+
+        assert!(parser.update_line(max, "last"));
+        assert_eq!(parser.get_line(max).unwrap().content, "last");
+        assert!(parser.update_line(2, "foo foo"));
+        assert_eq!(parser.lines[0].group.as_ref().unwrap().matches, 3);
+        assert_eq!(parser.matches(), 3);
+
+        assert!(parser.remove_line(3));
+        assert!(parser.get_line(3).is_none());
+        assert!(!parser.remove_line(3));
+        assert_eq!(parser.matches(), 2);
+
+        assert!(parser.remove_line(max));
+        assert!(parser.get_line(max).is_none());
+        assert!(parser.remove_line(1));
+        assert!(parser.get_line(2).is_none());
+        assert!(parser.lines.is_empty());
+
+        // edits are highlighted for the current search, within the match cap
+        let mut parser = Parser::new();
+        parser.set_raw(lines);
+        parser.set_max_matches(3);
+        parser.set_search("foo");
+        assert!(parser.update_line(1, "Build foo"));
+        assert!(parser.get_line(1).unwrap().highlights.is_empty());
+        assert!(parser.update_line(2, "bar"));
+        assert!(parser.update_line(1, "Build foo"));
+        assert_eq!(parser.get_line(1).unwrap().highlights.len(), 1);
+        assert_eq!(parser.matches(), 3);
+    }
+
+    #[test]
+    fn append_raw() {
+        let first = concat!(
+            "2024-01-15T00:14:43.0000000Z ##[group]Build\n",
+            "2024-01-15T00:14:44.0000000Z compiling\n",
+            "2024-01-15T00:14:45.0000000Z ##[endgroup]\n",
+            "2024-01-15T00:14:46.0000000Z done\n",
+        );
+        let second = concat!(
+            "2024-01-15T00:14:45.0000000Z ##[endgroup]\n",
+            "2024-01-15T00:14:46.0000000Z done\n",
+            "2024-01-15T00:14:47.0000000Z done\n",
+            "2024-01-15T00:14:48.0000000Z ##[error]failed\n",
+        );
+
+        let mut parser = Parser::new();
+        parser.append_raw(first);
+        let summary = parser.append_raw(second);
+        assert_eq!(summary.lines, 2);
+        assert_eq!(summary.errors, 1);
+
+        let content: Vec<(usize, &str)> = parser
+            .iter_display()
+            .map(|line| (line.number, line.content.as_str()))
+            .collect();
+        assert_eq!(
+            content,
+            vec![
+                (1, "Build"),
+                (2, "compiling"),
+                (3, "done"),
+                (4, "done"),
+                (5, "failed")
+            ]
+        );
+
+        // re-sending everything adds nothing, new lines without overlap are all added
+        assert_eq!(parser.append_raw(&parser.raw_text()).lines, 0);
+        let summary = parser.append_raw("2024-01-15T00:14:49.0000000Z next\n");
+        assert_eq!(summary.lines, 1);
+        assert_eq!(parser.append_raw("").lines, 0);
+
+        // an unterminated line waits for the next chunk, which either sends it again or continues it
+        let content = |parser: &Parser| -> Vec<String> {
+            parser
+                .lines
+                .iter()
+                .map(|line| line.content.clone())
+                .collect()
+        };
+
+        let mut parser = Parser::new();
+        assert_eq!(parser.append_raw("a\nbc").lines, 1);
+        assert_eq!(parser.append_raw("bcd\ne\n").lines, 2);
+        assert_eq!(content(&parser), vec!["a", "bcd", "e"]);
+
+        parser.append_raw("e\n\u{1b}[3");
+        parser.append_raw("1mred\nf");
+        parser.append_raw("");
+        assert_eq!(content(&parser), vec!["a", "bcd", "e", "red"]);
+        assert!(matches!(
+            &parser.lines[3].elements[0],
+            Element::Text(_, styles) if styles.fg == Some(Color::Bit4(1))
+        ));
+
+        parser.flush();
+        assert_eq!(content(&parser), vec!["a", "bcd", "e", "red", "f"]);
+    }
+
+    #[test]
+    fn persist_styles() {
+        let lines = concat!(
+            "\u{1b}[31mred\n",
+            "still red\n",
+            "##[group]\u{1b}[1mBuild\n",
+            "red and bold\n",
+            "##[endgroup]\u{1b}[22m\n",
+            "just red\u{1b}[0m\n",
+            "plain\n",
+        );
+
+        let fg = |parser: &Parser| -> Vec<(Option<Color>, bool)> {
+            parser
+                .iter_display()
+                .map(|line| match &line.elements[0] {
+                    Element::Text(_, styles) => (styles.fg.clone(), styles.bold),
+                    _ => unreachable!(),
+                })
+                .collect()
+        };
+
+        let red = Some(Color::Bit4(1));
+        let mut parser = Parser::new();
+        parser.set_raw(lines);
+        assert_eq!(
+            fg(&parser),
+            vec![
+                (red.clone(), false),
+                (None, false),
+                (None, true),
+                (None, false),
+                (None, false),
+                (None, false),
+            ]
+        );
+
+        parser.set_persist_styles(true);
+        parser.set_raw(lines);
+        assert_eq!(
+            fg(&parser),
+            vec![
+                (red.clone(), false),
+                (red.clone(), false),
+                (red.clone(), true),
+                (red.clone(), true),
+                (red.clone(), false),
+                (None, false),
+            ]
+        );
+
+        // the carried styles are part of the line's elements, not its own escapes
+        assert!(parser.lines[1].ansis.is_empty());
+        parser.add_line("", "streamed");
+        assert_eq!(fg(&parser).last(), Some(&(None, false)));
+    }
+
+    #[test]
+    fn dim_non_matches() {
+        let mut parser = Parser::new();
+        parser.set_raw("##[group]Build\nfoo bar\n##[endgroup]\nbaz\n");
+        parser.set_dim_non_matches(true);
+
+        // (text, dimmed) of every text element of the line or child with the number
+        let dimmed = |parser: &Parser, number: usize| -> Vec<(String, bool)> {
+            parser
+                .get_line(number)
+                .unwrap()
+                .elements
+                .iter()
+                .map(|element| match element {
+                    Element::Text(content, styles) => (content.clone(), styles.dim),
+                    _ => unreachable!(),
+                })
+                .collect()
+        };
+
+        // nothing is dimmed without a search
+        assert_eq!(dimmed(&parser, 2), vec![("foo bar".to_string(), false)]);
+
+        let changed = parser.set_search_diff("bar");
+        assert_eq!(changed, vec![1, 2, 3]);
+        assert_eq!(
+            dimmed(&parser, 2),
+            vec![("foo ".to_string(), true), ("bar".to_string(), false)]
+        );
+        assert_eq!(dimmed(&parser, 1), vec![("Build".to_string(), true)]);
+        assert_eq!(dimmed(&parser, 3), vec![("baz".to_string(), true)]);
+
+        // lines added during the search are dimmed too
+        parser.add_line("", "more bar");
+        assert_eq!(
+            dimmed(&parser, 4),
+            vec![("more ".to_string(), true), ("bar".to_string(), false)]
+        );
+
+        parser.clear_search();
+        assert_eq!(dimmed(&parser, 2), vec![("foo bar".to_string(), false)]);
+        assert_eq!(dimmed(&parser, 4), vec![("more bar".to_string(), false)]);
+
+        parser.set_search("bar");
+        parser.set_dim_non_matches(false);
+        assert_eq!(dimmed(&parser, 3), vec![("baz".to_string(), false)]);
+    }
+
+    #[test]
+    fn styles_at_matches_elements() {
+        let mut parser = Parser::new();
+        parser.set_persist_styles(true);
+        parser.set_level_colors(true);
+        parser.set_dim_non_matches(true);
+        parser.set_raw(concat!(
+            "\u{1b}[1mbold\n",
+            "still bold\n",
+            "##[error]bold and red\u{1b}[0m plain\n",
+        ));
+        parser.set_search("red");
+
+        for line in &parser.lines {
+            let mut index = 0;
+            for element in &line.elements {
+                let Element::Text(text, styles) = element else {
+                    unreachable!()
+                };
+                assert_eq!(&line.styles_at(index), styles, "{:?}", text);
+                index += text.len();
+            }
+        }
+        assert!(parser.lines[1].styles_at(0).bold);
+        assert_eq!(parser.lines[2].styles_at(0).fg, Some(Color::Bit4(1)));
+    }
+
+    #[test]
+    #[cfg(any(feature = "wasm", feature = "native"))]
+    fn dim_non_matches_restore() {
+        let mut parser = Parser::new();
+        parser.set_raw("\u{1b}[2mbar\u{1b}[0m plain\n");
+        parser.set_dim_non_matches(true);
+        parser.set_search("bar");
+
+        let dimmed = |line: &Line| -> Vec<(String, bool)> {
+            line.elements
+                .iter()
+                .map(|element| match element {
+                    Element::Text(content, styles) => (content.clone(), styles.dim),
+                    _ => unreachable!(),
+                })
+                .collect()
+        };
+
+        let want = vec![("bar".to_string(), true), (" plain".to_string(), false)];
+        assert_eq!(
+            dimmed(&parser.lines[0]),
+            vec![("bar".to_string(), true), (" plain".to_string(), true)]
+        );
+
+        // the faint match is kept as an escape, the dimming from the search is dropped
+        let json = to_json(&parser.lines, false).unwrap();
+        let loaded = Parser::from_lines(serde_json::from_str(&json).unwrap());
+        let line = &loaded.lines[0];
+        assert!(!line.dim_unmatched);
+        assert_eq!(line.raw, "\u{1b}[2mbar\u{1b}[0m plain");
+        assert_eq!(dimmed(line), want);
+
+        // a full reset ends faint text
+        parser.clear_search();
+        assert_eq!(dimmed(&parser.lines[0]), want);
+    }
+
+    #[test]
+    fn highlight_ranges() {
+        let mut parser = Parser::new();
+        parser.set_raw(concat!(
+            "##[group]Build\n",
+            "føø foo\n",
+            "##[endgroup]\n",
+            "nothing\n",
+            "FOO and foo\n",
+        ));
+        assert!(parser.highlight_ranges().is_empty());
+
+        parser.set_search("foo");
+        let ranges = parser.highlight_ranges();
+        assert_eq!(
+            ranges,
+            BTreeMap::from([(2, vec![(4, 7)]), (4, vec![(0, 3), (8, 11)])])
+        );
+
+        // the same spans as the highlights map, in utf-16 units
+        for (number, ranges) in &ranges {
+            let line = parser.get_line(*number).unwrap();
+            assert_eq!(ranges.len(), line.highlights.len());
+            for &(start, end) in ranges {
+                assert_eq!(line.utf16_highlights().get(&start), Some(&end));
+            }
+        }
+        let child = parser.get_line(2).unwrap();
+        assert_eq!(child.highlights, HashMap::from([(6, 9)]));
+
+        parser.clear_search();
+        assert!(parser.highlight_ranges().is_empty());
+    }
+
+    #[test]
+    fn to_plaintext_with_timestamps() {
+        let mut parser = Parser::new();
+        parser.set_raw(concat!(
+            "2024-01-15T00:14:43.0000000Z ##[group]Build\n",
+            "2024-01-15T00:14:44.5000000Z \u{1b}[1mcompiling\u{1b}[0m https://reb.gg\n",
+            "2024-01-15T00:14:45.0000000Z ##[endgroup]\n",
+            "2024-01-15T00:14:46.0000000Z done\n",
+            "no timestamp\n",
+        ));
+
+        assert_eq!(
+            parser.to_plaintext_with_timestamps("%H:%M:%S%.3f").unwrap(),
+            concat!(
+                "00:14:43.000 Build\n",
+                "  00:14:44.500 compiling https://reb.gg\n",
+                "00:14:46.000 done\n",
+                "no timestamp",
+            )
+        );
+        assert_eq!(
+            parser.to_plaintext_with_timestamps("%Q"),
+            Err(Error::InvalidTimestampFormat("%Q".to_string()))
+        );
+    }
+
+    #[test]
+    fn line_numbers_for_command() {
+        let mut parser = Parser::new();
+        parser.set_raw(concat!(
+            "##[error]first\n",
+            "##[group]Build\n",
+            "##[warning]careful\n",
+            "##[error]in a group\n",
+            "##[endgroup]\n",
+            "plain\n",
+            "##[error]last\n",
+        ));
+
+        let error = Command::Error as u8;
+        assert_eq!(parser.line_numbers_for_command(error), vec![1, 4, 6]);
+        assert_eq!(
+            parser.line_numbers_for_command(Command::Group as u8),
+            vec![2]
+        );
+        assert!(parser
+            .line_numbers_for_command(Command::Notice as u8)
+            .is_empty());
+        assert!(parser.line_numbers_for_command(200).is_empty());
+    }
+
+    #[test]
+    fn time_range() {
+        let mut parser = Parser::new();
+        assert_eq!(parser.time_range(), None);
+
+        parser.set_raw(concat!(
+            "2024-01-15T00:14:43.0000000Z ##[group]Build\n",
+            "2024-01-15T00:14:50.0000000Z last, in a group\n",
+            "2024-01-15T00:14:44.0000000Z ##[endgroup]\n",
+            "2024-01-15T00:14:42.0000000Z out of order\n",
+            "2024-01-15T00:14:45.0000000Z end\n",
+        ));
+        assert_eq!(parser.time_range(), Some((1705277682000, 1705277690000)));
+    }
+
+    #[test]
+    fn line_at_ts() {
+        let lines = concat!(
+            "2024-01-15T00:14:43.0000000Z one\n",
+            "2024-01-15T00:14:44.0000000Z two\n",
+            "2024-01-15T00:14:45.0000000Z ##[group]three\n",
+            "2024-01-15T00:14:46.0000000Z four\n",
+            "2024-01-15T00:14:47.0000000Z five\n",
+            "2024-01-15T00:14:48.0000000Z ##[endgroup]\n",
+            "2024-01-15T00:14:49.0000000Z six\n",
+        );
+
+        let mut parser = Parser::new();
+        parser.set_raw(lines);
+
+        // 2024-01-15T00:14:43.000Z
+        let start = 1705277683000;
+        assert_eq!(parser.line_at_ts(start - 1), Some(1));
+        assert_eq!(parser.line_at_ts(start), Some(1));
+        assert_eq!(parser.line_at_ts(start + 500), Some(2));
+        assert_eq!(parser.line_at_ts(start + 2000), Some(3));
+        assert_eq!(parser.line_at_ts(start + 3500), Some(5));
+        assert_eq!(parser.line_at_ts(start + 4500), Some(6));
+        assert_eq!(parser.line_at_ts(start + 6000), Some(6));
+        assert_eq!(parser.line_at_ts(start + 6001), None);
+    }
 
-        let expected_group = vec![true, false, true, false, false];
-        for (i, line) in parser.lines.iter().enumerate() {
-            match line.group {
-                Some(_) => {
-                    assert!(expected_group[i], "expected group");
-                }
-                _ => assert!(!expected_group[i], "did not expect group"),
-            }
-        }
+    #[test]
+    #[cfg(any(feature = "wasm", feature = "native"))]
+    fn base_timestamp() {
+        let lines = concat!(
+            "2024-01-15T00:14:43.0000000Z one\n",
+            "2024-01-15T00:14:43.2500000Z ##[group]two\n",
+            "2024-01-15T00:14:44.0000000Z three\n",
+            "2024-01-15T00:14:45.0000000Z ##[endgroup]\n",
+            "2024-01-15T00:14:50.0000000Z four\n",
+        );
 
-        // since the last two endgroups are not closing any groups, they are rendered as regular lines
-        parser.lines[3..].iter().for_each(|line| match line.cmd {
-            Some(Command::EndGroup) => {}
-            _ => panic!("expected Command::EndGroup"),
-        });
+        let mut parser = Parser::new();
+        parser.set_raw(lines);
+        assert!(parser.iter_display().all(|line| line.elapsed.is_none()));
+        assert!(!to_json(&parser.lines, false).unwrap().contains("\"el\""));
+
+        // 2024-01-15T00:14:43.5000000Z
+        parser.set_base_timestamp(1705277683500);
+        let elapsed: Vec<Option<i64>> = parser.iter_display().map(|line| line.elapsed).collect();
+        assert_eq!(elapsed, vec![Some(0), Some(0), Some(500), Some(6500)]);
+        assert!(to_json(&parser.lines[0], false)
+            .unwrap()
+            .contains("\"el\":0"));
+
+        parser.set_auto_base_timestamp(true);
+        let elapsed: Vec<Option<i64>> = parser.iter_display().map(|line| line.elapsed).collect();
+        assert_eq!(elapsed, vec![Some(0), Some(250), Some(1000), Some(7000)]);
+
+        // the base is detected again for new content
+        parser.set_raw("2024-01-15T00:15:00.0000000Z five\n2024-01-15T00:15:01.0000000Z six\n");
+        let elapsed: Vec<Option<i64>> = parser.iter_display().map(|line| line.elapsed).collect();
+        assert_eq!(elapsed, vec![Some(0), Some(1000)]);
+
+        parser.clear_base_timestamp();
+        assert!(parser.iter_display().all(|line| line.elapsed.is_none()));
     }
 
     #[test]
-    fn search() {
-        let lines = concat!("foo\n", "bar\n", "baz\n");
+    #[cfg(feature = "native")]
+    fn ndjson() {
+        let mut parser = Parser::new();
+        parser.set_include_raw_maps(true);
+        parser.set_raw("##[group]title\none https://reb.gg\ntwo\n##[endgroup]\nthree\n");
+
+        let ndjson = parser.to_ndjson().unwrap();
+        assert!(ndjson.ends_with('\n'));
+
+        let records: Vec<serde_json::Value> = ndjson
+            .lines()
+            .map(|record| serde_json::from_str(record).unwrap())
+            .collect();
+        assert_eq!(records.len(), parser.iter_display().count());
+
+        let fields: Vec<(u64, u64)> = records
+            .iter()
+            .map(|record| {
+                (
+                    record["n"].as_u64().unwrap(),
+                    record["depth"].as_u64().unwrap(),
+                )
+            })
+            .collect();
+        assert_eq!(fields, vec![(1, 0), (2, 1), (3, 1), (4, 0)]);
+
+        assert!(records[0]["group"].get("children").is_none());
+        assert_eq!(records[1]["links"]["4"], serde_json::json!([18, "Url"]));
+    }
+
+    #[test]
+    #[cfg(any(feature = "wasm", feature = "native"))]
+    fn show_debug() {
+        let lines = concat!(
+            "2024-01-15T00:14:43.0000000Z ##[debug]fetching cache\n",
+            "2024-01-15T00:14:43.0000000Z ##[group]Restore cache\n",
+            "2024-01-15T00:14:43.0000000Z cache hit\n",
+            "2024-01-15T00:14:43.0000000Z ##[debug]cache key abc\n",
+            "2024-01-15T00:14:43.0000000Z ##[endgroup]\n",
+            "2024-01-15T00:14:43.0000000Z done with cache\n",
+        );
 
         let mut parser = Parser::new();
         parser.set_raw(lines);
+        parser.set_search("cache");
 
-        let find_matches = |parser: &Parser| -> Vec<bool> {
-            parser.lines.iter().map(|line| line.matches() > 0).collect()
+        // (top level line numbers, child line numbers of the group)
+        let numbers = |parser: &Parser| {
+            let json: serde_json::Value =
+                serde_json::from_str(&parser.lines_json(false).unwrap()).unwrap();
+            let lines = json.as_array().unwrap();
+            let top: Vec<u64> = lines.iter().map(|l| l["n"].as_u64().unwrap()).collect();
+            let children: Vec<u64> = lines
+                .iter()
+                .flat_map(|l| {
+                    l["group"]["children"]
+                        .as_array()
+                        .cloned()
+                        .unwrap_or_default()
+                })
+                .map(|child| child["n"].as_u64().unwrap())
+                .collect();
+            (top, children)
         };
 
-        assert_eq!(parser.lines.len(), 3);
-        assert_eq!(find_matches(&parser), vec![false, false, false]);
+        assert_eq!(numbers(&parser), (vec![1, 2, 5], vec![3, 4]));
+        assert_eq!(parser.matches(), 5);
+        assert_eq!(parser.search_summary().len(), 5);
 
-        parser.set_search("bar");
-        assert_eq!(parser.matches(), 1);
-        assert_eq!(find_matches(&parser), vec![false, true, false]);
-        match parser.lines.get(1) {
-            Some(line) => assert_eq!(line.highlights, HashMap::from([(0, 3)])),
-            _ => panic!("expected line at index 1"),
+        parser.set_show_debug(false);
+        assert_eq!(numbers(&parser), (vec![2, 5], vec![3]));
+        assert_eq!(parser.matches(), 3);
+        assert_eq!(parser.search_summary(), vec![(2, 1), (3, 1), (5, 1)]);
+        assert!(!parser.lines_ndjson().unwrap().contains("cache key"));
+        assert!(!parser.lines_tree_json(false).unwrap().contains("fetching"));
+
+        // lines added while debug is hidden are hidden too
+        parser.add_line("", "2024-01-15T00:14:44.0000000Z ##[debug]cache saved");
+        assert_eq!(numbers(&parser), (vec![2, 5], vec![3]));
+        parser.set_include_raw_maps(true);
+        let json = parser.lines_json(false).unwrap();
+        assert!(!json.contains("cache key") && !json.contains("cache saved"));
+        parser.set_include_raw_maps(false);
+
+        parser.set_show_debug(true);
+        assert_eq!(numbers(&parser), (vec![1, 2, 5, 6], vec![3, 4]));
+        assert_eq!(parser.matches(), 6);
+    }
+
+    #[test]
+    fn level_colors() {
+        let mut parser = Parser::new();
+        parser.set_level_colors(true);
+        parser.set_infer_levels(true);
+        parser.set_raw("ERROR: boom\n##[error]\u{1b}[35mown color\nplain\n");
+
+        let fg = |parser: &Parser| -> Vec<Option<Color>> {
+            parser
+                .lines
+                .iter()
+                .map(|line| match &line.elements[0] {
+                    Element::Text(_, styles) => styles.fg.clone(),
+                    _ => None,
+                })
+                .collect()
+        };
+
+        let expected = vec![Some(Color::Bit4(1)), Some(Color::Bit4(5)), None];
+        assert_eq!(fg(&parser), expected);
+
+        // rebuilding elements for a search keeps the level color
+        parser.set_search("o");
+        assert_eq!(fg(&parser), expected);
+    }
+
+    #[test]
+    #[cfg(feature = "native")]
+    fn from_json() {
+        let lines = concat!(
+            "2024-01-15T00:14:43.0000000Z ##[group]\u{1b}[1mBuild\u{1b}[0m\n",
+            "2024-01-15T00:14:44.0000000Z \u{1b}[38;2;1;2;3;41mcolored\u{1b}[39m bg only\u{1b}[0m plain\n",
+            "2024-01-15T00:14:45.0000000Z see https://reb.gg or foo@reb.gg\n",
+            "2024-01-15T00:14:46.0000000Z ##[endgroup]\n",
+            "2024-01-15T00:14:47.0000000Z ##[error]\u{1b}[8mhidden\u{1b}[0m at src/main.rs:4:2\n",
+        );
+
+        let mut parser = Parser::new();
+        parser.set_detect_emails(true);
+        parser.set_detect_source_locations(true);
+        parser.set_raw(lines);
+        parser.set_search_terms(vec!["reb".to_string(), "main".to_string()]);
+
+        let json = to_json(&parser.lines, false).unwrap();
+        let mut loaded = Parser::from_json(&json).unwrap();
+        assert_eq!(to_json(&loaded.lines, false).unwrap(), json);
+        assert_eq!(loaded.idx, parser.idx);
+        assert_eq!(loaded.error_count(), 1);
+
+        // the maps are rebuilt, so elements rebuilt from them match the originals
+        for (restored, original) in loaded.iter_display().zip(parser.iter_display()) {
+            assert_eq!(restored.content, original.content);
+            assert_eq!(restored.links, original.links);
+            assert_eq!(restored.highlights, original.highlights);
+            assert_eq!(restored.highlight_groups, original.highlight_groups);
+            assert_eq!(crate::element::build_elements(restored), original.elements);
         }
+        assert_eq!(loaded.lines[0].matches(), parser.lines[0].matches());
 
-        parser.add_line("", "----> bar <----");
-        assert_eq!(parser.matches(), 2);
-        assert_eq!(find_matches(&parser), vec![false, true, false, true]);
-        match parser.lines.get(3) {
-            Some(line) => assert_eq!(line.highlights, HashMap::from([(6, 9)])),
-            _ => panic!("expected line at index 3"),
+        // searching a loaded parser behaves like the original
+        parser.set_search("plain");
+        loaded.set_search("plain");
+        assert_eq!(
+            to_json(&loaded.lines, false).unwrap(),
+            to_json(&parser.lines, false).unwrap()
+        );
+
+        assert!(Parser::from_json("[{\"n\": 1}]").is_err());
+    }
+
+    #[test]
+    fn match_snippets() {
+        let mut parser = Parser::new();
+        parser.set_raw("##[group]first error\nsome error here\n##[endgroup]\nno match\n");
+        parser.set_search("error");
+
+        assert_eq!(
+            parser.match_snippets(3),
+            vec![
+                (1, "…st error".to_string()),
+                (2, "…me error he…".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn errors() {
+        let mut parser = Parser::new();
+        parser.set_raw(concat!(
+            "##[error]outside\n",
+            "##[group]Build\n",
+            "fine\n",
+            "##[warning]careful\n",
+            "##[error]broken\n",
+            "##[endgroup]\n",
+            "##[group]Test\n",
+            "ok\n",
+            "##[endgroup]\n",
+            "##[warning]last\n",
+        ));
+
+        let errors: Vec<(usize, &str, Option<&str>)> = parser
+            .errors()
+            .into_iter()
+            .map(|(line, title)| (line.number, line.content.as_str(), title))
+            .collect();
+        assert_eq!(
+            errors,
+            vec![
+                (1, "outside", None),
+                (4, "careful", Some("Build")),
+                (5, "broken", Some("Build")),
+                (8, "last", None),
+            ]
+        );
+
+        #[cfg(feature = "wasm")]
+        {
+            let (line, group_title) = parser.errors()[1];
+            let value: serde_json::Value =
+                serde_json::to_value(ErrorLine { line, group_title }).unwrap();
+            assert_eq!(value["n"], 4);
+            assert_eq!(value["cmd"], 7);
+            assert_eq!(value["gt"], "Build");
         }
 
-        parser.add_line("", "#[group]some group");
-        parser.add_line("", "baz bar baz");
-        parser.add_line("", "#[endgroup]");
-        assert_eq!(parser.matches(), 3);
+        // groups titled from their first child
+        parser.set_group_title_from_child(true);
+        parser.set_raw(concat!(
+            "##[group]\n",
+            "Run tests\n",
+            "##[error]failed\n",
+            "##[endgroup]\n",
+        ));
+        let (line, title) = parser.errors()[0];
+        assert_eq!(line.content, "failed");
+        assert_eq!(title, Some("Run tests"));
+    }
 
-        parser.set_search("");
-        assert_eq!(parser.matches(), 0);
+    #[test]
+    fn sections() {
+        let mut parser = Parser::new();
+        parser.set_raw(concat!(
+            "##[section]Starting: build\n",
+            "##[group]Run make\n",
+            "make\n",
+            "##[section]Finishing: build\n",
+            "after\n",
+        ));
+
+        let lines: Vec<(usize, Option<Command>, bool)> = parser
+            .lines
+            .iter()
+            .map(|line| (line.number, line.cmd, line.group.is_some()))
+            .collect();
+        assert_eq!(
+            lines,
+            vec![
+                (1, Some(Command::Section), false),
+                (2, Some(Command::Group), true),
+                (4, Some(Command::Section), false),
+                (5, None, false),
+            ]
+        );
+        assert!(parser.lines[1].group.as_ref().unwrap().ended);
+        assert_eq!(parser.lines[2].content, "Finishing: build");
+    }
+
+    #[test]
+    fn max_matches() {
+        let mut parser = Parser::new();
+        parser.set_raw("##[group]x x\nx x x\n##[endgroup]\nx x x x\n");
+        parser.set_search("x");
+        assert_eq!(parser.search_summary(), vec![(1, 2), (2, 3), (3, 4)]);
+        assert!(!parser.matches_capped());
+
+        parser.set_max_matches(4);
+        assert_eq!(parser.search_summary(), vec![(1, 2), (2, 2)]);
+        assert!(parser.matches_capped());
+
+        // streamed lines share the cap
+        parser.set_max_matches(10);
+        assert!(!parser.matches_capped());
+        parser.add_line("", "x x x x x");
+        assert_eq!(
+            parser.search_summary(),
+            vec![(1, 2), (2, 3), (3, 4), (4, 1)]
+        );
+        assert!(parser.matches_capped());
+
+        // the cap starts over for a new search
+        parser.set_search("x x");
+        assert!(!parser.matches_capped());
+    }
+
+    #[test]
+    fn verbatim_groups() {
+        let lines = concat!(
+            "##[group]raw\n",
+            "##[error]echoed, not an error\n",
+            "##[group]nested\n",
+            "##[endgroup]\n",
+            "##[error]real\n",
+        );
+
+        let mut parser = Parser::new();
+        parser.set_verbatim_groups(true);
+        parser.set_raw(lines);
+
+        assert_eq!(parser.lines.len(), 2);
+        let children = &parser.lines[0].group.as_ref().unwrap().children;
+        let children: Vec<(Option<Command>, &str)> = children
+            .iter()
+            .map(|child| (child.cmd, child.content.as_str()))
+            .collect();
+        assert_eq!(
+            children,
+            vec![
+                (None, "##[error]echoed, not an error"),
+                (None, "##[group]nested")
+            ]
+        );
+        assert!(parser.lines[0].group.as_ref().unwrap().ended);
+        assert_eq!(parser.lines[1].cmd, Some(Command::Error));
+        assert_eq!(parser.error_count(), 1);
+
+        // other groups, and raw groups when disabled, are parsed as usual
+        parser.set_verbatim_groups(false);
+        parser.set_raw(lines);
+        assert_eq!(parser.error_count(), 2);
+    }
+
+    #[test]
+    fn diff() {
+        let mut parser = Parser::new();
+        parser.set_raw("one\n##[group]two\nthree\n");
+        parser.set_search("t");
+
+        let previous = parser.clone();
+        assert_eq!(parser.diff(&previous), Vec::<usize>::new());
+
+        // appending only reports the new lines
+        parser.add_line("", "four");
+        parser.add_line("", "five");
+        assert_eq!(parser.diff(&previous), vec![4, 5]);
+
+        // closing the group also changes its header
+        let previous = parser.clone();
+        parser.add_line("", "##[endgroup]");
+        parser.add_line("", "six");
+        assert_eq!(parser.diff(&previous), vec![2, 6]);
+
+        // re-searching changes the elements of the matching lines
+        let previous = parser.clone();
+        parser.set_search("f");
+        assert_eq!(parser.diff(&previous), vec![2, 3, 4, 5]);
+    }
+
+    #[test]
+    #[cfg(any(feature = "wasm", feature = "native"))]
+    fn reserve_lines() {
+        let mut reserved = Parser::new();
+        reserved.reserve_lines(1000);
+        assert!(reserved.lines.capacity() >= 1000);
+
+        let mut parser = Parser::new();
+        for i in 0..1000 {
+            let raw = match i % 10 {
+                0 => format!("2024-01-15T00:14:49.2830954Z ##[group]group {}", i),
+                9 => "2024-01-15T00:14:49.2830954Z ##[endgroup]".to_string(),
+                _ => format!("2024-01-15T00:14:49.2830954Z line {} https://reb.gg", i),
+            };
+            parser.add_line("", &raw);
+            reserved.add_line("", &raw);
+        }
+
+        assert_eq!(
+            to_json(&reserved, false).unwrap(),
+            to_json(&parser, false).unwrap()
+        );
+    }
+
+    #[test]
+    fn truncate_tail() {
+        let lines = concat!(
+            "one\n",
+            "##[group]two\n",
+            "three\n",
+            "four\n",
+            "##[endgroup]\n",
+            "five\n",
+            "six\n",
+        );
+
+        let mut parser = Parser::new();
+        parser.set_raw(lines);
+
+        parser.truncate_tail(10);
+        assert_eq!(parser.lines.len(), 4);
+
+        parser.truncate_tail(3);
+        let numbers: Vec<usize> = parser.iter_display().map(|line| line.number).collect();
+        assert_eq!(numbers, vec![2, 3, 4, 5, 6]);
+
+        parser.truncate_tail(1);
+        assert_eq!(parser.lines.len(), 1);
+        assert_eq!(parser.lines[0].content, "six");
+
+        // new lines keep numbering after the dropped ones
+        parser.add_line("", "seven");
+        assert_eq!(parser.lines[1].number, 7);
+
+        parser.truncate_tail(0);
+        assert!(parser.lines.is_empty());
+    }
+
+    #[test]
+    fn error_count() {
+        let lines = concat!(
+            "##[error]one\n",
+            "##[warning]two\n",
+            "##[info]three\n",
+            "##[group]four\n",
+            "##[error]five\n",
+            "ERROR: six\n",
+            "##[endgroup]\n",
+            "warn: seven\n",
+        );
+
+        let mut parser = Parser::new();
+        parser.set_raw(lines);
+        assert_eq!(parser.error_count(), 2);
+        assert_eq!(parser.warning_count(), 1);
+
+        parser.set_infer_levels(true);
+        let summary = parser.set_raw(lines);
+        assert_eq!(parser.error_count(), 3);
+        assert_eq!(parser.warning_count(), 2);
+        assert_eq!(
+            summary,
+            Summary {
+                lines: 7,
+                groups: 1,
+                errors: 3,
+                warnings: 2,
+            }
+        );
+    }
+
+    #[test]
+    fn iter_display() {
+        let lines = concat!(
+            "2024-01-15T00:14:49.2830954Z ##[group]Operating System\n",
+            "2024-01-15T00:14:49.2831846Z Ubuntu\n",
+            "2024-01-15T00:14:49.2832204Z 22.04.3\n",
+            "2024-01-15T00:14:49.2832638Z LTS\n",
+            "2024-01-15T00:14:49.2833085Z ##[endgroup]\n",
+            "2024-01-15T00:14:49.2833509Z ##[group]Runner Image\n",
+            "2024-01-15T00:14:49.2834023Z Image: ubuntu-22.04\n",
+            "2024-01-15T00:14:49.2834552Z Version: 20240107.1.0\n",
+            "2024-01-15T00:14:49.2838476Z ##[endgroup]\n",
+            "2024-01-15T00:14:49.2839497Z outside\n",
+        );
+
+        let mut parser = Parser::new();
+        parser.set_raw(lines);
+
+        let content: Vec<&str> = parser
+            .iter_display()
+            .map(|line| line.content.as_str())
+            .collect();
+
+        assert_eq!(
+            content,
+            vec![
+                "Operating System",
+                "Ubuntu",
+                "22.04.3",
+                "LTS",
+                "Runner Image",
+                "Image: ubuntu-22.04",
+                "Version: 20240107.1.0",
+                "outside",
+            ]
+        );
+
+        let numbers: Vec<usize> = parser.iter_display().map(|line| line.number).collect();
+        assert_eq!(numbers, (1..=8).collect::<Vec<usize>>());
+    }
+
+    #[test]
+    #[cfg(any(feature = "wasm", feature = "native"))]
+    fn group_title_from_child() {
+        let lines = concat!(
+            "2024-01-15T00:14:43.0000000Z ##[group]\n",
+            "2024-01-15T00:14:43.0000000Z Run actions/checkout@v4\n",
+            "2024-01-15T00:14:43.0000000Z with:\n",
+            "2024-01-15T00:14:43.0000000Z ##[endgroup]\n",
+            "2024-01-15T00:14:43.0000000Z ##[group]Named\n",
+            "2024-01-15T00:14:43.0000000Z child\n",
+            "2024-01-15T00:14:43.0000000Z ##[endgroup]\n",
+        );
+
+        let mut parser = Parser::new();
+        parser.set_raw(lines);
+        assert_eq!(parser.lines[0].group.as_ref().unwrap().title, None);
+
+        parser.set_group_title_from_child(true);
+        parser.set_raw(lines);
+
+        let group = parser.lines[0].group.as_ref().unwrap();
+        assert_eq!(group.title.as_deref(), Some("Run actions/checkout@v4"));
+        assert_eq!(group.children.len(), 2);
+        assert_eq!(group.children[0].content, "Run actions/checkout@v4");
+
+        // headers with content keep their own title
+        assert_eq!(parser.lines[1].group.as_ref().unwrap().title, None);
+
+        assert_eq!(parser.find_group("run actions/checkout@v4"), Some(1));
+        assert_eq!(
+            parser.groups_outline(),
+            vec![
+                (1, "Run actions/checkout@v4".to_string(), 2),
+                (4, "Named".to_string(), 1)
+            ]
+        );
+
+        let json = parser.lines_json(false).unwrap();
+        assert!(json.contains(r#""title":"Run actions/checkout@v4""#));
+        assert_eq!(json.matches(r#""title""#).count(), 1);
     }
 }
@@ -1,21 +1,83 @@
 use serde::ser::{SerializeTuple, Serializer};
-use serde::Serialize;
+use serde::{Deserialize, Deserializer, Serialize};
 
 use crate::ansi::ANSISequence;
 
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum Color {
+    // one of the 16 colors set with 30-37/90-97 (or 40-47/100-107), usually themed by the terminal
+    Bit4(u8),
+    // an index into the 256 color palette, set with 38;5;n (or 48;5;n)
     Bit8(u8),
     Bit24(u8, u8, u8),
 }
 
+const NAMES: [&str; 16] = [
+    "black",
+    "red",
+    "green",
+    "yellow",
+    "blue",
+    "magenta",
+    "cyan",
+    "white",
+    "bright black",
+    "bright red",
+    "bright green",
+    "bright yellow",
+    "bright blue",
+    "bright magenta",
+    "bright cyan",
+    "bright white",
+];
+
+impl Color {
+    // the ansi name of the 16 4-bit colors (or the same indexes of the 8-bit palette), other colors
+    // have no name
+    pub fn name(&self) -> Option<&'static str> {
+        match *self {
+            Color::Bit4(idx) | Color::Bit8(idx) => NAMES.get(idx as usize).copied(),
+            Color::Bit24(..) => None,
+        }
+    }
+
+    // the color as rgb, 4-bit and 8-bit colors use the standard xterm palette
+    pub fn rgb(&self) -> (u8, u8, u8) {
+        match *self {
+            Color::Bit4(idx) | Color::Bit8(idx) => bit8_to_rgb(idx),
+            Color::Bit24(r, g, b) => (r, g, b),
+        }
+    }
+}
+
+// https://www.w3.org/TR/WCAG21/#dfn-relative-luminance
+fn relative_luminance((r, g, b): (u8, u8, u8)) -> f32 {
+    let channel = |c: u8| {
+        let c = c as f32 / 255.0;
+        if c <= 0.03928 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    0.2126 * channel(r) + 0.7152 * channel(g) + 0.0722 * channel(b)
+}
+
+// https://www.w3.org/TR/WCAG21/#dfn-contrast-ratio, from 1 (no contrast) to 21 (black on white)
+fn contrast_ratio(a: &Color, b: &Color) -> f32 {
+    let (a, b) = (relative_luminance(a.rgb()), relative_luminance(b.rgb()));
+    (a.max(b) + 0.05) / (a.min(b) + 0.05)
+}
+
 impl Serialize for Color {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: Serializer,
     {
         match *self {
-            Color::Bit8(value) => serializer.serialize_u8(value),
+            // 4-bit colors serialize as their index like palette colors, Styles tags them so
+            // renderers can theme them (see StylesRepr)
+            Color::Bit4(value) | Color::Bit8(value) => serializer.serialize_u8(value),
             Color::Bit24(r, g, b) => {
                 let mut tuple = serializer.serialize_tuple(3)?;
                 tuple.serialize_element(&r)?;
@@ -27,41 +89,314 @@ impl Serialize for Color {
     }
 }
 
-#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize)]
+impl<'de> Deserialize<'de> for Color {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        // the inverse of serialize, a number for 8-bit colors and an [r, g, b] array for 24-bit.
+        // Styles turns tagged numbers back into 4-bit colors
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Repr {
+            Bit8(u8),
+            Bit24(u8, u8, u8),
+        }
+
+        Ok(match Repr::deserialize(deserializer)? {
+            Repr::Bit8(value) => Color::Bit8(value),
+            Repr::Bit24(r, g, b) => Color::Bit24(r, g, b),
+        })
+    }
+}
+
+// standard xterm values for the 16 4-bit colors
+const PALETTE_4BIT: [(u8, u8, u8); 16] = [
+    (0, 0, 0),
+    (205, 0, 0),
+    (0, 205, 0),
+    (205, 205, 0),
+    (0, 0, 238),
+    (205, 0, 205),
+    (0, 205, 205),
+    (229, 229, 229),
+    (127, 127, 127),
+    (255, 0, 0),
+    (0, 255, 0),
+    (255, 255, 0),
+    (92, 92, 255),
+    (255, 0, 255),
+    (0, 255, 255),
+    (255, 255, 255),
+];
+
+// converts an 8-bit color index to rgb
+// https://en.wikipedia.org/wiki/ANSI_escape_code#8-bit
+pub fn bit8_to_rgb(idx: u8) -> (u8, u8, u8) {
+    match idx {
+        0..=15 => PALETTE_4BIT[idx as usize],
+        // 6x6x6 cube, idx = 16 + 36*r + 6*g + b
+        16..=231 => {
+            let level = |n: u8| if n == 0 { 0 } else { 55 + 40 * n };
+            let cube = idx - 16;
+            (level(cube / 36), level((cube / 6) % 6), level(cube % 6))
+        }
+        // 24 step grayscale ramp
+        232..=255 => {
+            let gray = 8 + 10 * (idx - 232);
+            (gray, gray, gray)
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(into = "StylesRepr", from = "StylesRepr")]
 pub struct Styles {
-    #[serde(rename = "b", skip_serializing_if = "std::ops::Not::not")]
     pub bold: bool,
-    #[serde(rename = "i", skip_serializing_if = "std::ops::Not::not")]
+    // faint text, also used for text outside of search matches when dimming non-matches
+    pub dim: bool,
     pub italic: bool,
-    #[serde(rename = "u", skip_serializing_if = "std::ops::Not::not")]
     pub underline: bool,
-    #[serde(rename = "hl", skip_serializing_if = "std::ops::Not::not")]
     pub highlight: bool,
-    #[serde(skip_serializing_if = "Option::is_none")]
+    // which of multiple search terms the highlight matched, renderers can color them differently
+    pub highlight_group: Option<u8>,
+    // fg and bg are kept as set, renderers should swap them when reversed (see effective_fg/bg)
+    pub reverse: bool,
+    // concealed text should be rendered hidden, but is still part of the content for search
+    pub conceal: bool,
+    // renderers should animate blinking text with a css class rather than an inline style
+    pub blink: bool,
     pub fg: Option<Color>,
-    #[serde(skip_serializing_if = "Option::is_none")]
     pub bg: Option<Color>,
+    // underlines are drawn in fg unless set
+    pub underline_color: Option<Color>,
+}
+
+// the serialized form of Styles. 4-bit colors are numbers like palette indexes (as they were before
+// the two were told apart), with a tag so renderers can theme the 16 standard colors
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+struct StylesRepr {
+    #[serde(rename = "b", skip_serializing_if = "std::ops::Not::not")]
+    bold: bool,
+    #[serde(rename = "d", skip_serializing_if = "std::ops::Not::not")]
+    dim: bool,
+    #[serde(rename = "i", skip_serializing_if = "std::ops::Not::not")]
+    italic: bool,
+    #[serde(rename = "u", skip_serializing_if = "std::ops::Not::not")]
+    underline: bool,
+    #[serde(rename = "hl", skip_serializing_if = "std::ops::Not::not")]
+    highlight: bool,
+    #[serde(rename = "hg", skip_serializing_if = "Option::is_none")]
+    highlight_group: Option<u8>,
+    #[serde(rename = "r", skip_serializing_if = "std::ops::Not::not")]
+    reverse: bool,
+    #[serde(rename = "h", skip_serializing_if = "std::ops::Not::not")]
+    conceal: bool,
+    #[serde(rename = "bl", skip_serializing_if = "std::ops::Not::not")]
+    blink: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fg: Option<Color>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bg: Option<Color>,
+    #[serde(rename = "uc", skip_serializing_if = "Option::is_none")]
+    underline_color: Option<Color>,
+    #[serde(rename = "f4", skip_serializing_if = "std::ops::Not::not")]
+    fg_4bit: bool,
+    #[serde(rename = "b4", skip_serializing_if = "std::ops::Not::not")]
+    bg_4bit: bool,
+    #[serde(rename = "u4", skip_serializing_if = "std::ops::Not::not")]
+    underline_color_4bit: bool,
+}
+
+impl From<Styles> for StylesRepr {
+    fn from(styles: Styles) -> Self {
+        let is_4bit = |color: &Option<Color>| matches!(color, Some(Color::Bit4(_)));
+        Self {
+            fg_4bit: is_4bit(&styles.fg),
+            bg_4bit: is_4bit(&styles.bg),
+            underline_color_4bit: is_4bit(&styles.underline_color),
+            bold: styles.bold,
+            dim: styles.dim,
+            italic: styles.italic,
+            underline: styles.underline,
+            highlight: styles.highlight,
+            highlight_group: styles.highlight_group,
+            reverse: styles.reverse,
+            conceal: styles.conceal,
+            blink: styles.blink,
+            fg: styles.fg,
+            bg: styles.bg,
+            underline_color: styles.underline_color,
+        }
+    }
+}
+
+impl From<StylesRepr> for Styles {
+    fn from(repr: StylesRepr) -> Self {
+        let color = |color: Option<Color>, is_4bit: bool| match color {
+            Some(Color::Bit8(idx)) if is_4bit && idx < 16 => Some(Color::Bit4(idx)),
+            color => color,
+        };
+        Self {
+            bold: repr.bold,
+            dim: repr.dim,
+            italic: repr.italic,
+            underline: repr.underline,
+            highlight: repr.highlight,
+            highlight_group: repr.highlight_group,
+            reverse: repr.reverse,
+            conceal: repr.conceal,
+            blink: repr.blink,
+            fg: color(repr.fg, repr.fg_4bit),
+            bg: color(repr.bg, repr.bg_4bit),
+            underline_color: color(repr.underline_color, repr.underline_color_4bit),
+        }
+    }
 }
 
 impl Styles {
     pub fn new() -> Self {
         Self {
             bold: false,
+            dim: false,
             italic: false,
             underline: false,
             highlight: false,
+            highlight_group: None,
+            reverse: false,
+            conceal: false,
+            blink: false,
             fg: None,
             bg: None,
+            underline_color: None,
+        }
+    }
+
+    // the sequences that set these styles, starting from no styles. Highlights aren't ansi styles
+    // so they're left out
+    pub fn to_ansis(&self) -> Vec<ANSISequence> {
+        let flags = [
+            (self.bold, ANSISequence::Bold),
+            (self.dim, ANSISequence::Dim),
+            (self.italic, ANSISequence::Italic),
+            (self.underline, ANSISequence::Underline),
+            (self.reverse, ANSISequence::Reverse),
+            (self.conceal, ANSISequence::Conceal),
+            (self.blink, ANSISequence::Blink),
+        ];
+
+        let mut seqs: Vec<ANSISequence> = flags
+            .into_iter()
+            .filter_map(|(set, seq)| set.then_some(seq))
+            .collect();
+
+        match self.fg {
+            Some(Color::Bit4(color)) => seqs.push(ANSISequence::SetFG4(color)),
+            Some(Color::Bit8(color)) => seqs.push(ANSISequence::SetFG8(color)),
+            Some(Color::Bit24(r, g, b)) => seqs.push(ANSISequence::SetFG24(r, g, b)),
+            None => {}
+        }
+        match self.bg {
+            Some(Color::Bit4(color)) => seqs.push(ANSISequence::SetBG4(color)),
+            Some(Color::Bit8(color)) => seqs.push(ANSISequence::SetBG8(color)),
+            Some(Color::Bit24(r, g, b)) => seqs.push(ANSISequence::SetBG24(r, g, b)),
+            None => {}
+        }
+        match self.underline_color {
+            Some(Color::Bit4(color) | Color::Bit8(color)) => {
+                seqs.push(ANSISequence::SetUnderlineColor8(color))
+            }
+            Some(Color::Bit24(r, g, b)) => seqs.push(ANSISequence::SetUnderlineColor24(r, g, b)),
+            None => {}
+        }
+
+        seqs
+    }
+
+    // layers over on top of self, e.g. a line's styles over a theme's defaults. Colors and the
+    // highlight group set in over win, unset ones fall through to self. Flags are OR'd together,
+    // since an unset flag can't be told apart from one that was reset, over can't turn off a flag
+    // that self sets
+    pub fn merge(&self, over: &Styles) -> Styles {
+        Styles {
+            bold: self.bold || over.bold,
+            dim: self.dim || over.dim,
+            italic: self.italic || over.italic,
+            underline: self.underline || over.underline,
+            highlight: self.highlight || over.highlight,
+            highlight_group: over.highlight_group.or(self.highlight_group),
+            reverse: self.reverse || over.reverse,
+            conceal: self.conceal || over.conceal,
+            blink: self.blink || over.blink,
+            fg: over.fg.clone().or_else(|| self.fg.clone()),
+            bg: over.bg.clone().or_else(|| self.bg.clone()),
+            underline_color: over
+                .underline_color
+                .clone()
+                .or_else(|| self.underline_color.clone()),
         }
     }
 
     pub fn is_empty(&self) -> bool {
         !self.bold
+            && !self.dim
             && !self.italic
             && !self.underline
             && !self.highlight
+            && self.highlight_group.is_none()
+            && !self.reverse
+            && !self.conceal
+            && !self.blink
             && self.fg.is_none()
             && self.bg.is_none()
+            && self.underline_color.is_none()
+    }
+
+    // the foreground color as displayed, accounting for reverse video
+    pub fn effective_fg(&self) -> Option<&Color> {
+        if self.reverse {
+            self.bg.as_ref()
+        } else {
+            self.fg.as_ref()
+        }
+    }
+
+    // the background color as displayed, accounting for reverse video
+    pub fn effective_bg(&self) -> Option<&Color> {
+        if self.reverse {
+            self.fg.as_ref()
+        } else {
+            self.bg.as_ref()
+        }
+    }
+
+    // when the displayed foreground and background are both set and their wcag contrast ratio is
+    // under min_ratio (4.5 is the wcag AA level for text), the foreground becomes black or white,
+    // whichever contrasts more with the background. Otherwise the styles are returned as is
+    pub fn contrast_adjust(&self, min_ratio: f32) -> Styles {
+        let mut adjusted = self.clone();
+        let (Some(fg), Some(bg)) = (self.effective_fg(), self.effective_bg()) else {
+            return adjusted;
+        };
+        if contrast_ratio(fg, bg) >= min_ratio {
+            return adjusted;
+        }
+
+        let black = Color::Bit24(0, 0, 0);
+        let white = Color::Bit24(255, 255, 255);
+        let legible = if contrast_ratio(&black, bg) >= contrast_ratio(&white, bg) {
+            black
+        } else {
+            white
+        };
+
+        if self.reverse {
+            adjusted.bg = Some(legible);
+        } else {
+            adjusted.fg = Some(legible);
+        }
+        adjusted
     }
 
     pub fn apply_ansis(&mut self, ansis: &Vec<ANSISequence>) {
@@ -74,31 +409,223 @@ impl Styles {
         match ansi {
             ANSISequence::Reset => {
                 self.bold = false;
+                self.dim = false;
                 self.italic = false;
                 self.underline = false;
+                self.reverse = false;
+                self.conceal = false;
+                self.blink = false;
                 self.fg = None;
                 self.bg = None;
+                self.underline_color = None;
             }
             ANSISequence::Bold => self.bold = true,
+            ANSISequence::Dim => self.dim = true,
             ANSISequence::Italic => self.italic = true,
             ANSISequence::Underline => self.underline = true,
-            ANSISequence::NotBold => self.bold = false,
+            // 22 is normal intensity, neither bold nor faint
+            ANSISequence::NotBold => {
+                self.bold = false;
+                self.dim = false;
+            }
             ANSISequence::NotItalic => self.italic = false,
             ANSISequence::NotUnderline => self.underline = false,
+            ANSISequence::Reverse => self.reverse = true,
+            ANSISequence::NotReverse => self.reverse = false,
+            ANSISequence::Conceal => self.conceal = true,
+            ANSISequence::NotConceal => self.conceal = false,
+            ANSISequence::Blink => self.blink = true,
+            ANSISequence::NotBlink => self.blink = false,
+            ANSISequence::SetFG4(color) => self.fg = Some(Color::Bit4(*color)),
             ANSISequence::SetFG8(color) => self.fg = Some(Color::Bit8(*color)),
             ANSISequence::DefaultFG => self.fg = None,
+            ANSISequence::SetBG4(color) => self.bg = Some(Color::Bit4(*color)),
             ANSISequence::SetBG8(color) => self.bg = Some(Color::Bit8(*color)),
             ANSISequence::DefaultBG => self.bg = None,
             ANSISequence::SetFG24(r, g, b) => self.fg = Some(Color::Bit24(*r, *g, *b)),
             ANSISequence::SetBG24(r, g, b) => self.bg = Some(Color::Bit24(*r, *g, *b)),
+            ANSISequence::SetUnderlineColor8(color) => {
+                self.underline_color = Some(Color::Bit8(*color))
+            }
+            ANSISequence::SetUnderlineColor24(r, g, b) => {
+                self.underline_color = Some(Color::Bit24(*r, *g, *b))
+            }
+            ANSISequence::DefaultUnderlineColor => self.underline_color = None,
         }
     }
 }
 
+impl Default for Styles {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn merge() {
+        let base = Styles {
+            fg: Some(Color::Bit8(7)),
+            bg: Some(Color::Bit8(0)),
+            ..Styles::new()
+        };
+
+        let bold = Styles {
+            bold: true,
+            ..Styles::new()
+        };
+        assert_eq!(
+            base.merge(&bold),
+            Styles {
+                bold: true,
+                fg: Some(Color::Bit8(7)),
+                bg: Some(Color::Bit8(0)),
+                ..Styles::new()
+            }
+        );
+
+        let red = Styles {
+            fg: Some(Color::Bit24(255, 0, 0)),
+            ..Styles::new()
+        };
+        assert_eq!(
+            base.merge(&red),
+            Styles {
+                fg: Some(Color::Bit24(255, 0, 0)),
+                bg: Some(Color::Bit8(0)),
+                ..Styles::new()
+            }
+        );
+
+        // flags set in the base can't be unset
+        assert!(bold.merge(&Styles::new()).bold);
+        assert_eq!(Styles::new().merge(&base), base);
+    }
+
+    #[test]
+    fn deserialize() {
+        let styles: Styles = serde_json::from_str(r#"{"b":true,"fg":1,"bg":[1,2,3]}"#).unwrap();
+        assert_eq!(
+            styles,
+            Styles {
+                bold: true,
+                fg: Some(Color::Bit8(1)),
+                bg: Some(Color::Bit24(1, 2, 3)),
+                ..Styles::new()
+            }
+        );
+        assert_eq!(
+            serde_json::from_str::<Styles>(&serde_json::to_string(&styles).unwrap()).unwrap(),
+            styles
+        );
+
+        let mut applied = Styles::new();
+        applied.apply_ansis(&styles.to_ansis());
+        assert_eq!(applied, styles);
+    }
+
+    #[test]
+    fn contrast_adjust() {
+        // blue on black is under the AA ratio, so the text becomes white
+        let styles = Styles {
+            bold: true,
+            fg: Some(Color::Bit4(4)),
+            bg: Some(Color::Bit8(0)),
+            ..Styles::new()
+        };
+        let adjusted = styles.contrast_adjust(4.5);
+        assert_eq!(adjusted.fg, Some(Color::Bit24(255, 255, 255)));
+        assert_eq!(adjusted.bg, styles.bg);
+        assert!(adjusted.bold);
+
+        // yellow on white becomes black
+        let styles = Styles {
+            fg: Some(Color::Bit24(255, 255, 0)),
+            bg: Some(Color::Bit4(15)),
+            ..Styles::new()
+        };
+        assert_eq!(styles.contrast_adjust(4.5).fg, Some(Color::Bit24(0, 0, 0)));
+
+        // reversed, the displayed foreground is bg
+        let styles = Styles {
+            reverse: true,
+            fg: Some(Color::Bit8(0)),
+            bg: Some(Color::Bit4(4)),
+            ..Styles::new()
+        };
+        let adjusted = styles.contrast_adjust(4.5);
+        assert_eq!(adjusted.fg, styles.fg);
+        assert_eq!(adjusted.bg, Some(Color::Bit24(255, 255, 255)));
+
+        // high contrast pairs and styles with a single color are left alone
+        let high = Styles {
+            fg: Some(Color::Bit4(15)),
+            bg: Some(Color::Bit4(0)),
+            ..Styles::new()
+        };
+        assert_eq!(high.contrast_adjust(4.5), high);
+        let fg_only = Styles {
+            fg: Some(Color::Bit8(17)),
+            ..Styles::new()
+        };
+        assert_eq!(fg_only.contrast_adjust(21.0), fg_only);
+    }
+
+    #[test]
+    fn serialize_4bit() {
+        // ESC[31m is the themeable red, ESC[38;5;1m is palette index 1
+        let mut bit4 = Styles::new();
+        bit4.apply_ansis(&vec![ANSISequence::SetFG4(1), ANSISequence::SetBG4(9)]);
+        let mut bit8 = Styles::new();
+        bit8.apply_ansis(&vec![ANSISequence::SetFG8(1), ANSISequence::SetBG8(9)]);
+
+        let bit4_json = serde_json::to_string(&bit4).unwrap();
+        let bit8_json = serde_json::to_string(&bit8).unwrap();
+        assert_eq!(bit4_json, r#"{"fg":1,"bg":9,"f4":true,"b4":true}"#);
+        assert_eq!(bit8_json, r#"{"fg":1,"bg":9}"#);
+
+        assert_eq!(serde_json::from_str::<Styles>(&bit4_json).unwrap(), bit4);
+        assert_eq!(serde_json::from_str::<Styles>(&bit8_json).unwrap(), bit8);
+        // only the 16 standard colors can be 4-bit
+        assert_eq!(
+            serde_json::from_str::<Styles>(r#"{"fg":200,"f4":true}"#).unwrap(),
+            Styles {
+                fg: Some(Color::Bit8(200)),
+                ..Styles::new()
+            }
+        );
+    }
+
+    #[test]
+    fn color_name() {
+        assert_eq!(Color::Bit8(0).name(), Some("black"));
+        assert_eq!(Color::Bit8(1).name(), Some("red"));
+        assert_eq!(Color::Bit8(9).name(), Some("bright red"));
+        assert_eq!(Color::Bit8(15).name(), Some("bright white"));
+        assert_eq!(Color::Bit8(200).name(), None);
+        assert_eq!(Color::Bit24(205, 0, 0).name(), None);
+    }
+
+    #[test]
+    fn bit8_rgb() {
+        let cases = [
+            (1, (205, 0, 0)),
+            (16, (0, 0, 0)),
+            (124, (175, 0, 0)),
+            (110, (135, 175, 215)),
+            (231, (255, 255, 255)),
+            (232, (8, 8, 8)),
+            (255, (238, 238, 238)),
+        ];
+
+        for (idx, expected) in cases {
+            assert_eq!(bit8_to_rgb(idx), expected, "index {}", idx);
+        }
+    }
+
     #[test]
     fn is_empty() {
         let mut styles = Styles::new();
@@ -132,6 +659,34 @@ mod tests {
                     ..Styles::new()
                 },
             ),
+            (
+                ANSISequence::Reverse,
+                Styles {
+                    reverse: true,
+                    ..Styles::new()
+                },
+            ),
+            (
+                ANSISequence::Dim,
+                Styles {
+                    dim: true,
+                    ..Styles::new()
+                },
+            ),
+            (
+                ANSISequence::Conceal,
+                Styles {
+                    conceal: true,
+                    ..Styles::new()
+                },
+            ),
+            (
+                ANSISequence::Blink,
+                Styles {
+                    blink: true,
+                    ..Styles::new()
+                },
+            ),
             (
                 ANSISequence::NotBold,
                 Styles {
@@ -195,6 +750,20 @@ mod tests {
                     ..Styles::new()
                 },
             ),
+            (
+                ANSISequence::SetUnderlineColor8(1),
+                Styles {
+                    underline_color: Some(Color::Bit8(1)),
+                    ..Styles::new()
+                },
+            ),
+            (
+                ANSISequence::SetUnderlineColor24(1, 2, 3),
+                Styles {
+                    underline_color: Some(Color::Bit24(1, 2, 3)),
+                    ..Styles::new()
+                },
+            ),
         ];
 
         for (ansi, expected) in cases {
@@ -211,10 +780,36 @@ mod tests {
                 ANSISequence::Reset,
                 Styles {
                     bold: true,
+                    dim: true,
                     italic: true,
                     underline: true,
+                    reverse: true,
+                    conceal: true,
+                    blink: true,
                     fg: Some(Color::Bit8(1)),
                     bg: Some(Color::Bit8(2)),
+                    underline_color: Some(Color::Bit8(3)),
+                    ..Styles::new()
+                },
+            ),
+            (
+                ANSISequence::NotConceal,
+                Styles {
+                    conceal: true,
+                    ..Styles::new()
+                },
+            ),
+            (
+                ANSISequence::NotReverse,
+                Styles {
+                    reverse: true,
+                    ..Styles::new()
+                },
+            ),
+            (
+                ANSISequence::NotBlink,
+                Styles {
+                    blink: true,
                     ..Styles::new()
                 },
             ),
@@ -225,6 +820,14 @@ mod tests {
                     ..Styles::new()
                 },
             ),
+            // normal intensity also ends faint text
+            (
+                ANSISequence::NotBold,
+                Styles {
+                    dim: true,
+                    ..Styles::new()
+                },
+            ),
             (
                 ANSISequence::NotItalic,
                 Styles {
@@ -253,6 +856,13 @@ mod tests {
                     ..Styles::new()
                 },
             ),
+            (
+                ANSISequence::DefaultUnderlineColor,
+                Styles {
+                    underline_color: Some(Color::Bit8(1)),
+                    ..Styles::new()
+                },
+            ),
         ];
 
         for (ansi, before) in cases {
@@ -262,6 +872,24 @@ mod tests {
         }
     }
 
+    #[test]
+    fn reverse_default_colors() {
+        let mut styles = Styles::new();
+        styles.apply_ansis(&vec![ANSISequence::SetFG8(1), ANSISequence::Reverse]);
+        assert_eq!(styles.effective_fg(), None);
+        assert_eq!(styles.effective_bg(), Some(&Color::Bit8(1)));
+
+        // default fg mid-reverse resets what the viewer sees as the background
+        styles.apply_ansi(&ANSISequence::DefaultFG);
+        assert_eq!(styles.effective_fg(), None);
+        assert_eq!(styles.effective_bg(), None);
+
+        styles.apply_ansis(&vec![ANSISequence::SetBG8(2), ANSISequence::DefaultBG]);
+        assert_eq!(styles.effective_fg(), None);
+        assert_eq!(styles.effective_bg(), None);
+        assert!(styles.reverse);
+    }
+
     #[test]
     fn does_not_reset_highlight() {
         let mut styles = Styles::new();
@@ -283,13 +911,19 @@ mod tests {
             (
                 Styles {
                     bold: true,
+                    dim: true,
                     italic: true,
                     underline: true,
                     highlight: true,
+                    highlight_group: Some(2),
+                    reverse: true,
+                    conceal: true,
+                    blink: true,
                     fg: Some(Color::Bit8(1)),
                     bg: Some(Color::Bit24(1, 2, 3)),
+                    underline_color: Some(Color::Bit8(9)),
                 },
-                r#"{"b":true,"i":true,"u":true,"hl":true,"fg":1,"bg":[1,2,3]}"#,
+                r#"{"b":true,"d":true,"i":true,"u":true,"hl":true,"hg":2,"r":true,"h":true,"bl":true,"fg":1,"bg":[1,2,3],"uc":9}"#,
             ),
         ];
 
@@ -0,0 +1,62 @@
+use chrono::format::{Item, StrftimeItems};
+use chrono::{Local, TimeZone, Utc};
+
+// TimestampFormat formats unix millisecond timestamps with a strftime pattern
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimestampFormat {
+    fmt: String,
+    utc: bool,
+}
+
+impl TimestampFormat {
+    // returns None if the pattern contains invalid strftime specifiers
+    pub fn new(fmt: &str, utc: bool) -> Option<Self> {
+        if StrftimeItems::new(fmt).any(|item| item == Item::Error) {
+            return None;
+        }
+
+        Some(Self {
+            fmt: fmt.to_string(),
+            utc,
+        })
+    }
+
+    pub fn format(&self, unix_ms: i64) -> Option<String> {
+        let ts = Utc.timestamp_millis_opt(unix_ms).single()?;
+
+        let formatted = if self.utc {
+            ts.format(&self.fmt).to_string()
+        } else {
+            ts.with_timezone(&Local).format(&self.fmt).to_string()
+        };
+
+        Some(formatted)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invalid() {
+        assert!(TimestampFormat::new("%Y-%m-%d", true).is_some());
+        assert!(TimestampFormat::new("%Q", true).is_none());
+        assert!(TimestampFormat::new("%", true).is_none());
+    }
+
+    #[test]
+    fn format() {
+        let iso = TimestampFormat::new("%Y-%m-%dT%H:%M:%S%.3fZ", true).unwrap();
+        assert_eq!(
+            iso.format(1705277683580),
+            Some("2024-01-15T00:14:43.580Z".to_string())
+        );
+
+        let custom = TimestampFormat::new("%H:%M:%S on %b %d", true).unwrap();
+        assert_eq!(
+            custom.format(1705277683580),
+            Some("00:14:43 on Jan 15".to_string())
+        );
+    }
+}
@@ -0,0 +1,35 @@
+#![cfg(feature = "native")]
+
+use actionslogs::Parser;
+use serde_json::Value;
+
+#[test]
+fn parse_and_stringify() {
+    let lines = concat!(
+        "2024-01-15T00:14:49.2830954Z ##[group]Operating System\n",
+        "2024-01-15T00:14:49.2831846Z Ubuntu\n",
+        "2024-01-15T00:14:49.2832204Z 22.04.3\n",
+        "2024-01-15T00:14:49.2833085Z ##[endgroup]\n",
+        "2024-01-15T00:14:49.2833509Z ##[error]\u{1b}[31mfailed\u{1b}[0m https://reb.gg\n",
+    );
+
+    let mut parser = Parser::new();
+    parser.set_raw(lines);
+
+    let json: Value = serde_json::from_str(&parser.stringify(false).unwrap()).unwrap();
+    let lines = json.as_array().unwrap();
+    assert_eq!(lines.len(), 2);
+
+    assert_eq!(lines[0]["n"], 1);
+    assert_eq!(lines[0]["cmd"], 8);
+    assert_eq!(lines[0]["group"]["children"].as_array().unwrap().len(), 2);
+
+    assert_eq!(lines[1]["n"], 4);
+    assert_eq!(lines[1]["cmd"], 3);
+    assert_eq!(lines[1]["ts"], 1705277689283_i64);
+    assert_eq!(lines[1]["elements"][0]["styles"]["fg"], 1);
+    assert_eq!(lines[1]["elements"][0]["styles"]["f4"], true);
+    assert_eq!(lines[1]["elements"][2]["href"], "https://reb.gg");
+
+    assert!(parser.set_timestamp_format("%Q", true).is_err());
+}
@@ -46,11 +46,36 @@ const JSObject: React.FC<PreviewProps> = ({ lines }) => {
     }
 };
 
-const colorToCSS = (color: Color): string => {
+// the standard xterm values of the 16 4-bit colors
+const ANSI_COLORS = [
+    "#000000",
+    "#cd0000",
+    "#00cd00",
+    "#cdcd00",
+    "#0000ee",
+    "#cd00cd",
+    "#00cdcd",
+    "#e5e5e5",
+    "#7f7f7f",
+    "#ff0000",
+    "#00ff00",
+    "#ffff00",
+    "#5c5cff",
+    "#ff00ff",
+    "#00ffff",
+    "#ffffff",
+];
+
+const colorToCSS = (color: Color, bit4 = false): string => {
     if (Array.isArray(color)) {
         return `rgb(${color.join(",")})`;
     }
 
+    // 4-bit colors can be themed with --ansi-0 through --ansi-15
+    if (bit4 && color < ANSI_COLORS.length) {
+        return `var(--ansi-${color}, ${ANSI_COLORS[color]})`;
+    }
+
     return (
         [
             "black",
@@ -87,6 +112,18 @@ const renderElement = (element: Element): JSX.Element | null => {
         );
     }
 
+    if ("path" in element) {
+        const location = [element.path, element.line, element.col]
+            .filter((part) => part !== undefined)
+            .join(":");
+
+        return (
+            <span className="source-ref" title={location}>
+                {element.children.map((child) => renderElement(child))}
+            </span>
+        );
+    }
+
     if ("content" in element) {
         const style: React.CSSProperties = {};
 
@@ -94,6 +131,10 @@ const renderElement = (element: Element): JSX.Element | null => {
             style.fontWeight = "bold";
         }
 
+        if (element.styles.d) {
+            style.opacity = 0.5;
+        }
+
         if (element.styles.i) {
             style.fontStyle = "italic";
         }
@@ -102,18 +143,43 @@ const renderElement = (element: Element): JSX.Element | null => {
             style.textDecoration = "underline";
         }
 
+        if (element.styles.uc) {
+            style.textDecorationColor = colorToCSS(
+                element.styles.uc,
+                element.styles.u4,
+            );
+        }
+
+        if (element.styles.h) {
+            style.visibility = "hidden";
+        }
+
         if (element.styles.hl) {
-            style.color = "yellow";
+            // each term of a multi-term search gets its own color
+            const colors = ["yellow", "orange", "lime", "cyan", "violet"];
+            style.color = colors[(element.styles.hg ?? 0) % colors.length];
         }
 
-        if (element.styles.fg) {
-            style.color = colorToCSS(element.styles.fg);
+        const { fg, bg, f4, b4 } = element.styles;
+        const [[fore, fore4], [back, back4]] = element.styles.r
+            ? [[bg, b4], [fg, f4]]
+            : [[fg, f4], [bg, b4]];
+
+        if (fore) {
+            style.color = colorToCSS(fore, fore4);
         }
 
-        if (element.styles.bg) {
-            style.backgroundColor = colorToCSS(element.styles.bg);
+        if (back) {
+            style.backgroundColor = colorToCSS(back, back4);
         }
-        return <span style={style}>{element.content}</span>;
+        // blinking is animated by the "blink" class rather than an inline style
+        const className = element.styles.bl ? "blink" : undefined;
+
+        return (
+            <span style={style} className={className}>
+                {element.content}
+            </span>
+        );
     }
 
     return null;
@@ -123,9 +189,9 @@ const renderLine = (line: Line): JSX.Element => {
     if (line.group) {
         return (
             <>
-                <details key={line.n}>
+                <details key={line.n} open={!line.group.c}>
                     <summary>
-                        {line.n} {line.elements.map(renderElement)}
+                        {line.n} {line.group.title ?? line.elements.map(renderElement)}
                     </summary>
                     {line.group.children.map(renderLine)}
                 </details>
@@ -4,14 +4,31 @@ import type { InitInput } from "../../gen/actionslogs/actionslogs";
 export interface Line {
     n: number;
     ts: number;
+    tf?: string;
+    // the line had no timestamp, ts is when it was parsed
+    tsy?: boolean;
+    el?: number;
     cmd?: Command;
+    params?: Record<string, string>;
     elements: Element[];
     group?: Group;
+    truncated?: boolean;
+    // text outside of search matches is dimmed
+    dm?: boolean;
+    // only included when setIncludeRawMaps(true), keyed by byte index
+    links?: Record<string, [number, "Url" | "Email" | "Source"]>;
+    ansis?: Record<string, unknown[]>;
+    highlights?: Record<string, number>;
+    // the same maps keyed by utf-16 index, for slicing js strings
+    links_utf16?: Record<string, [number, "Url" | "Email" | "Source"]>;
+    highlights_utf16?: Record<string, number>;
 }
 
 export interface Group {
     children: Line[];
     ended: boolean;
+    c: boolean;
+    title?: string;
 }
 
 export enum Command {
@@ -24,29 +41,55 @@ export enum Command {
     Warning = 7,
     Group = 8,
     EndGroup = 9,
+    Section = 10,
 }
 
-export type Element = TextElement | LinkElement | string;
+// unstyled text is serialized as a plain string, everything else is tagged by type
+export type Element = TextElement | LinkElement | SourceRefElement | string;
 
 export interface TextElement {
+    type: "text";
     content: string;
     styles: Styles;
 }
 
 export interface LinkElement {
+    type: "link";
     href: string;
     children: Element[];
+    kind: "url" | "email";
+    has_match?: boolean;
+}
+
+export interface SourceRefElement {
+    type: "sourceref";
+    path: string;
+    line: number;
+    col?: number;
+    children: Element[];
 }
 
 export interface Styles {
     b?: boolean;
+    d?: boolean;
     i?: boolean;
     u?: boolean;
     hl?: boolean;
+    hg?: number;
+    r?: boolean;
+    h?: boolean;
+    bl?: boolean;
     fg?: Color;
     bg?: Color;
+    uc?: Color;
+    // set when fg, bg or uc is one of the 16 4-bit colors, which can be themed, rather than an
+    // index of the 256 color palette
+    f4?: boolean;
+    b4?: boolean;
+    u4?: boolean;
 }
 
+// palette indexes (or 4-bit colors, see f4/b4/u4) and 24-bit colors as [r, g, b]
 export type Color = number | [number, number, number];
 
 class Parser extends actionslogs.Parser {